@@ -0,0 +1,58 @@
+// Move generation and board serialization, on a midgame position where
+// both sides have plenty of moves. Run with `cargo bench`
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::sync::atomic::AtomicBool;
+
+use tungstenite_testings::othello::ai::Strategy;
+use tungstenite_testings::othello::bitboard::LARGE;
+use tungstenite_testings::othello::moves::*;
+use tungstenite_testings::othello::perft::perft;
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+// A midgame position, black to move
+const MIDGAME : &str = concat!(
+    "..oooo..",
+    "o.oooo..",
+    "oooo@o..",
+    "oo@@@@@.",
+    "o@o@@@..",
+    "oo@@@...",
+    "o..@@...",
+    "........",
+);
+
+fn midgame() -> BoardStruct {
+    MIDGAME.parse().unwrap()
+}
+
+fn move_generation(c: &mut Criterion) {
+    let board = midgame();
+    c.bench_function("legal_moves", |b| b.iter(|| legal_moves(black_box(&Player::Black), black_box(&board))));
+    c.bench_function("make_move", |b| {
+        let square = legal_moves(&Player::Black, &board)[0];
+        b.iter(|| {
+            let mut after = board;
+            make_move(black_box(&square), &Player::Black, &mut after).unwrap()
+        })
+    });
+    c.bench_function("perft 5", |b| b.iter(|| perft(black_box(&BoardStruct::new()), &Player::Black, 5)));
+    c.bench_function("perft 4 10x10", |b| b.iter(|| perft(black_box(&BoardStruct::initial(&LARGE)), &Player::Black, 4)));
+}
+
+fn search(c: &mut Criterion) {
+    let board = midgame();
+    let stop = AtomicBool::new(false);
+    c.bench_function("alphabeta 4", |b| {
+        b.iter(|| Strategy::AlphaBeta(4).choose_move(black_box(&board), &Player::Black, false, &stop))
+    });
+}
+
+fn serialization(c: &mut Criterion) {
+    let board = midgame();
+    let json = serde_json::to_string(&board).unwrap();
+    c.bench_function("serialize board", |b| b.iter(|| serde_json::to_string(black_box(&board)).unwrap()));
+    c.bench_function("deserialize board", |b| b.iter(|| serde_json::from_str::<BoardStruct>(black_box(&json)).unwrap()));
+}
+
+criterion_group!(benches, move_generation, search, serialization);
+criterion_main!(benches);
@@ -8,7 +8,7 @@ use std::io::{
     ErrorKind as IOErrorKind,
 };
 use std::sync::{Arc, Mutex};
-use futures_channel::mpsc::{unbounded, UnboundedSender};
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures_util::{
     pin_mut,
     sink::Sink,
@@ -23,10 +23,11 @@ use futures_util::{
 use log::*;
 use std::marker::Unpin;
 use tokio::io::{
-    AsyncRead,
-    AsyncWrite,
+    AsyncReadExt,
+    AsyncWriteExt,
 };
-use tokio_tungstenite::WebSocketStream;
+use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Duration, Instant};
 use tungstenite::{
     Message as WSMessage,
     Result as WSResult,
@@ -34,38 +35,68 @@ use tungstenite::{
 };
 
 use crate::protocol::*;
-use crate::runner::{self, Runner, settings};
+use crate::runner::{self, Backend, settings, backend::Resigned, limits::LimitExceeded};
 use crate::othello::{
     BoardStruct,
     Player,
     moves::*,
+    record::GameRecord,
+    openings,
+    eval,
+    result::{GameResult, EndReason},
+    variant::Variant,
+    render::{self, SvgOptions},
 };
 
-const SEND_TIMEOUT : u64 = 10; // Number of seconds to wait before treating send to client as a failure
+const MAX_BAD_REPLIES : usize = 3; // Number of unusable move replies a human can send before forfeiting
+const MAX_HTTP_HEAD : usize = 8192; // Number of bytes a plain HTTP request can have before the body
 
 type Tx = UnboundedSender<ServerMessage>;
 pub type PeerMap = Arc<Mutex<HashMap<Id, Tx>>>;
 
 pub enum PlayerType {
     Human,
-    Ai(Runner),
+    // Anything the server runs or connects to itself
+    Ai(Box<dyn Backend>),
 }
 
-pub struct Game {
-    black: PlayerType,
-    white: PlayerType,
-    board: BoardStruct,
+// Everything about a game that stays the same from one move to the next
+struct GameContext<'a> {
+    id: &'a Id,
+    room_map: &'a RoomMap,
+    peer_map: &'a PeerMap,
+    black_name: String,
+    white_name: String,
+    timelimit: f32,
+    variant: Variant,
+    format: Notation,
 }
 
+impl GameContext<'_> {
+    // The same game with the players the other way around
+    fn swapped(&self) -> Self {
+        GameContext {
+            black_name: self.white_name.clone(),
+            white_name: self.black_name.clone(),
+            ..*self
+        }
+    }
+}
+
+// Handles messages that arrive while we aren't waiting on a move from this client
 pub async fn handle_incoming_message(
     id: &Id,
-    room_map: &RoomMap,
+    _room_map: &RoomMap,
     client_msg: ClientMessage,
 ) {
     match client_msg {
         ClientMessage::MoveReply {square} => {
-            // TODO: you know the drill
-            info!("move_reply on square {} from {}", square, id);
+            // Moves are only read while a MoveRequest is outstanding, see get_human_move
+            info!("ignoring out-of-turn move_reply on square {} from {}", square, id);
+        },
+        ClientMessage::Resign {} => {
+            // Resignations are acted on while the game is being played, see tick_game_with_timeout
+            info!("ignoring resignation from {} outside of a game", id);
         },
         ClientMessage::Disconnect {} => {
             info!("disconnect signaled from {}", id);
@@ -99,17 +130,31 @@ fn send_peer_message(
     peer_map: &PeerMap,
     msg: &ServerMessage,
 ) -> WSResult<()> {
-    let room_map = room_map.lock().unwrap();
-    let room = room_map.get(game_id);
+    update_and_send_peer_message(game_id, room_map, peer_map, |_| (), msg)
+}
+
+// Updates the room of a game, then sends a message to all its peers.
+// Both happen under the room lock so that a watcher joining at the same time
+// sees the update in exactly one of its snapshot or the message
+fn update_and_send_peer_message<F: FnOnce(&mut Room)>(
+    game_id: &Id,
+    room_map: &RoomMap,
+    peer_map: &PeerMap,
+    update: F,
+    msg: &ServerMessage,
+) -> WSResult<()> {
+    let mut room_map = room_map.lock().unwrap();
+    let room = room_map.get_mut(game_id);
     if room.is_none() {
         return Err(WSError::Io(IOError::new(IOErrorKind::NotFound, format!("Tried to send to peers of game id {}, but it wasn't found!", game_id).as_str())));
     }
     let room = room.unwrap();
+    update(room);
 
     let peers = peer_map.lock().unwrap();
     let broadcast_recipients : Vec<Option<&Tx>> = room.watching
         .iter()
-        .map(|id| { peers.get(id).clone() })
+        .map(|id| peers.get(id))
         .collect();
     
     // FIXME: currently "ignoring" errors here.
@@ -138,103 +183,330 @@ async fn send_message<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + U
     room_map: &RoomMap,
     peer_map: &PeerMap,
     ws_sender: &mut T,
+    format: Notation,
+    msg: &ServerMessage,
+) -> WSResult<()> {
+    update_and_send_message(game_id, room_map, peer_map, ws_sender, format, |_| (), msg).await
+}
+
+async fn update_and_send_message<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin, F: FnOnce(&mut Room)>(
+    game_id: &Id,
+    room_map: &RoomMap,
+    peer_map: &PeerMap,
+    ws_sender: &mut T,
+    format: Notation,
+    update: F,
     msg: &ServerMessage,
 ) -> WSResult<()> {
     debug!("Sending message {:?}", msg);
     
-    match send_ws_message(ws_sender, msg).await {
-        Ok(()) => send_peer_message(game_id, room_map, peer_map, msg),
+    // Peers get the message as-is, and convert it to their own format.
+    // Players never see the evaluation, since it would be giving them hints
+    match send_ws_message(ws_sender, &msg.in_format(format).without_eval()).await {
+        Ok(()) => update_and_send_peer_message(game_id, room_map, peer_map, update, msg),
         Err(why) => {
             send_peer_message(game_id, room_map, peer_map,
                 &ServerMessage::GameError {
                     error: format!("Error sending original message: {}", why),
                 }
             )?;
-            send_peer_message(game_id, room_map, peer_map, msg)?;
+            update_and_send_peer_message(game_id, room_map, peer_map, update, msg)?;
             Err(why)
         }
     }
 }
 
+fn is_human(how: &PlayerType) -> bool {
+    matches!(how, PlayerType::Human)
+}
+
+fn resignation(player: Player) -> WSError {
+    WSError::Io(IOError::other(Resigned(player)))
+}
 
-fn make_player(name: &String) -> WSResult<PlayerType> {
+// Who gave up, if that's what the error is
+fn resigned(why: &WSError) -> Option<Player> {
+    match why {
+        WSError::Io(io) => io.get_ref().and_then(|inner| inner.downcast_ref::<Resigned>()).map(|Resigned(player)| *player),
+        _ => None,
+    }
+}
+
+// Errors from getting/making a move that lose the game for somebody rather
+// than erroring it out. `player` is the one whose turn it was
+fn forfeit(why: &WSError, player: &Player, black: &PlayerType, white: &PlayerType) -> Option<(Player, EndReason)> {
+    let how = match player {
+        Player::Black => black,
+        Player::White => white,
+        Player::Unknown => return None,
+    };
+    if let Some(quitter) = resigned(why) {
+        return Some((quitter, EndReason::Resignation));
+    }
+    // Only whoever is on the socket can leave, and they can do it on the
+    // other side's turn
+    let human = if is_human(how) {
+        Some(*player)
+    } else if is_human(black) {
+        Some(Player::Black)
+    } else if is_human(white) {
+        Some(Player::White)
+    } else {
+        None
+    };
+
+    match why {
+        WSError::ConnectionClosed | WSError::AlreadyClosed => human.map(|p| (p, EndReason::Disconnect)),
+        WSError::Io(io) if io.get_ref().is_some_and(|inner| inner.is::<LimitExceeded>()) => {
+            Some((*player, EndReason::ResourceLimit))
+        },
+        WSError::Io(io) => match io.kind() {
+            IOErrorKind::TimedOut => Some((*player, EndReason::Timeout)),
+            IOErrorKind::InvalidInput => Some((*player, EndReason::IllegalMove)),
+            // Anything else going wrong is the socket for a human, and the
+            // program's own fault for everyone else
+            _ if is_human(how) => Some((*player, EndReason::Disconnect)),
+            _ => Some((*player, EndReason::Crash)),
+        },
+        _ => None,
+    }
+}
+
+// The illegal move behind an error from apply_move, if that's what it was
+fn illegal_move(why: &WSError) -> Option<&IllegalMoveError> {
+    match why {
+        WSError::Io(io) => io.get_ref().and_then(|inner| inner.downcast_ref::<IllegalMoveError>()),
+        _ => None,
+    }
+}
+
+fn opening_name(game: &GameRecord) -> Option<String> {
+    openings::recognize(game).map(|opening| opening.name.to_string())
+}
+
+fn board_update(game: &GameRecord, black_name: &str, white_name: &str) -> ServerMessage {
+    let board = game.board();
+    let tomove = game.tomove();
+    let (black_discs, white_discs) = board.bitboards(&Player::Black);
+    // Skip over the pass that may have followed the move
+    let last_move = game.moves()
+        .iter()
+        .rev()
+        .find(|played| played.square.is_some());
+
+    // That's a lot of clones... ah well
+    ServerMessage::BoardUpdate {
+        board: *board,
+        tomove,
+        black: black_name.to_string(),
+        white: white_name.to_string(),
+        opening: opening_name(game),
+        last_move: last_move.map(PlayedMove::from),
+        legal_moves: legal_moves(&tomove, board).into_iter().map(Square::from).collect(),
+        black_discs: black_discs.count_ones(),
+        white_discs: white_discs.count_ones(),
+        eval: Some(eval::evaluate(board)),
+    }
+}
+
+fn game_end(game: &GameRecord, result: GameResult, forfeit_reason: Option<String>) -> ServerMessage {
+    ServerMessage::GameEnd {
+        board: *game.board(),
+        winner: result.outcome.winner(),
+        forfeit: result.is_forfeit(),
+        result,
+        opening: opening_name(game),
+        forfeit_reason,
+        report: eval::report(game),
+    }
+}
+
+async fn make_player(name: &String, variant: &Variant) -> WSResult<PlayerType> {
     if name == settings::HUMAN_PLAYER {
         return Ok(PlayerType::Human);
     }
-    
-    match runner::make_runner(name) {
-        Ok(runner) => Ok(PlayerType::Ai(runner)),
-        Err(why) => Err(WSError::Io(why.into())),
+
+    let mut backend = runner::make_backend(name, variant).map_err(WSError::Io)?;
+    match backend.start().await {
+        Ok(()) => Ok(PlayerType::Ai(backend)),
+        Err(why) => {
+            // Whatever did get started still has to be put away
+            if let Err(stop_why) = backend.stop().await {
+                warn!("Could not stop {} after it failed to start: {}", name, stop_why);
+            }
+            Err(WSError::Io(why))
+        },
+    }
+}
+
+// Prompts a human over the websocket until they give a legal move, run out
+// of time, or send too many bad replies
+async fn get_human_move<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin, T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
+    board: &BoardStruct,
+    player: &Player,
+    timelimit: f32,
+    format: Notation,
+    ws_sender: &mut T,
+    ws_receiver: &mut R,
+) -> WSResult<usize> {
+    let request = ServerMessage::MoveRequest {
+        tomove: *player,
+        squares: legal_moves(player, board).into_iter().map(Square::from).collect(),
+        timelimit,
+    }.in_format(format);
+    // Re-prompting doesn't reset the clock
+    let deadline = Instant::now() + Duration::from_millis((timelimit * 1000.0) as u64);
+    let mut bad_replies = 0;
+
+    send_ws_message(ws_sender, &request).await?;
+    loop {
+        let reply = match timeout_at(deadline, ws_receiver.next()).await {
+            Ok(Some(reply)) => reply?,
+            Ok(None) => return Err(WSError::AlreadyClosed),
+            Err(_) => {
+                return Err(WSError::Io(IOError::new(IOErrorKind::TimedOut, "Human player ran out of time!")));
+            },
+        };
+
+        let problem = match reply {
+            WSMessage::Close(_) => return Err(WSError::ConnectionClosed),
+            WSMessage::Text(_) => match unwrap_incomming_message(reply) {
+                Ok(ClientMessage::MoveReply {square}) => match format.decode(&square) {
+                    Ok(sq) => match check_move(&sq, player, board) {
+                        Ok(()) => return Ok(sq),
+                        Err(ill) => {
+                            // Let the client know exactly what was wrong
+                            send_ws_message(ws_sender, &ServerMessage::from(&ill).in_format(format)).await?;
+                            ill.to_string()
+                        },
+                    },
+                    Err(why) => why,
+                },
+                Ok(ClientMessage::Resign {}) => return Err(resignation(*player)),
+                Ok(ClientMessage::Disconnect {}) => return Err(WSError::ConnectionClosed),
+                Err(why) => format!("Could not understand move reply: {}", why),
+            },
+            // Pings, pongs, and binary messages don't count against the player
+            _ => continue,
+        };
+
+        bad_replies += 1;
+        if bad_replies >= MAX_BAD_REPLIES {
+            return Err(WSError::Io(IOError::new(IOErrorKind::InvalidInput, format!("{}, and too many bad replies were sent", problem).as_str())));
+        }
+        send_ws_message(ws_sender, &ServerMessage::GameError {error: problem}).await?;
+        send_ws_message(ws_sender, &request).await?;
     }
 }
 
-async fn get_move<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
+async fn get_move<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin, T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
     board: &BoardStruct,
     player: &Player,
     timelimit: f32,
     how: &mut PlayerType,
+    format: Notation,
     ws_sender: &mut T,
+    ws_receiver: Option<&mut R>,
 ) -> WSResult<usize> {
     match how {
-        PlayerType::Human => Err(WSError::Io(IOError::new(IOErrorKind::AddrNotAvailable, "Playing as human not implemented!"))),
-        PlayerType::Ai(r) => {
-            match runner::get_move(r, board, player, timelimit).await {
-                Ok(res) => Ok(res),
-                Err(why) => Err(WSError::Io(why)),
-            }
+        PlayerType::Human => match ws_receiver {
+            Some(rx) => get_human_move(board, player, timelimit, format, ws_sender, rx).await,
+            None => Err(WSError::Io(IOError::new(IOErrorKind::NotConnected, "Human player has no socket to read moves from!"))),
+        },
+        PlayerType::Ai(backend) => {
+            let deadline = Instant::now() + Duration::from_millis((timelimit * 1000.0) as u64);
+            backend.get_move(board, *player, deadline).await.map_err(WSError::Io)
+        },
+    }
+}
+
+// Humans keep up through the board updates instead
+async fn notify_player(how: &mut PlayerType, played: &MoveRecord) -> WSResult<()> {
+    match how {
+        PlayerType::Human => Ok(()),
+        PlayerType::Ai(backend) => backend.notify(played).await.map_err(WSError::Io),
+    }
+}
+
+// Plays a move, and the pass after it if the next player is stuck
+fn apply_move(game: &mut GameRecord, square: usize) -> WSResult<Option<Player>> {
+    // The error goes along inside the io::Error so play_main can report it
+    if let Err(ill) = game.play(square) {
+        return Err(WSError::Io(IOError::new(IOErrorKind::InvalidInput, ill)));
+    }
+    if game.must_pass() {
+        debug!("{:?} has no moves and passes", game.tomove());
+        if let Err(ill) = game.pass() {
+            return Err(WSError::Io(IOError::new(IOErrorKind::InvalidData, ill)));
         }
     }
+
+    match game.tomove() {
+        Player::Unknown => Ok(None),
+        p => Ok(Some(p)),
+    }
 }
 
-async fn tick_game<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
-    board: &mut BoardStruct,
-    player: Player,
+async fn tick_game<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin, T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
+    game: &mut GameRecord,
     timelimit: f32,
     black: &mut PlayerType,
     white: &mut PlayerType,
+    format: Notation,
     ws_sender: &mut T,
+    ws_receiver: Option<&mut R>,
 ) -> WSResult<Option<Player>> {
-    match player {
-        Player::Unknown => Ok(Some(Player::Unknown)),
-        Player::Black => {
-            let p = Player::Black;
-            let square = get_move(board, &p, timelimit, black, ws_sender).await?;
-            if let Err(ill) = make_move(&square, &p, board) {
-                return Err(WSError::Io(IOError::new(IOErrorKind::InvalidInput, format!("{:?}", ill).as_str())));
-            }
-
-            Ok(next_player(board, &p))
+    let player = game.tomove();
+    let (mover, other) = match player {
+        Player::Unknown => {
+            return Err(WSError::Io(IOError::new(IOErrorKind::InvalidData, "Tried to tick a game with nobody to move!")));
         },
-        Player::White => {
-            let p = Player::White;
-            let square = get_move(board, &p, timelimit, white, ws_sender).await?;
-            if let Err(ill) = make_move(&square, &p, board) {
-                return Err(WSError::Io(IOError::new(IOErrorKind::InvalidInput, format!("{:?}", ill).as_str())));
-            }
+        Player::Black => (black, white),
+        Player::White => (white, black),
+    };
+    let square = get_move(game.board(), &player, timelimit, mover, format, ws_sender, ws_receiver).await?;
+    let before = game.moves().len();
+    let next = apply_move(game, square)?;
 
-            Ok(next_player(board, &p))
+    // The move, and the other side's pass if they have to. Whoever moved
+    // already knows about their own move
+    for (i, played) in game.moves()[before..].iter().enumerate() {
+        if i > 0 {
+            notify_player(mover, played).await?;
         }
+        notify_player(other, played).await?;
     }
+    Ok(next)
 }
 
 async fn tick_game_with_timeout<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin, T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
-    board: &mut BoardStruct,
+    ctx: &GameContext<'_>,
+    game: &mut GameRecord,
     player: Player,
-    timelimit: f32,
     black: &mut PlayerType,
     white: &mut PlayerType,
     ws_sender: &mut T,
     ws_receiver: &mut R,
 ) -> WSResult<Option<Player>> {
-    
-    let mut tick_fut = tick_game(board, player, timelimit, black, white, ws_sender);
+    let GameContext {id: my_id, room_map, timelimit, format, ..} = *ctx;
+
+    let human_to_move = match player {
+        Player::Black => is_human(black),
+        Player::White => is_human(white),
+        Player::Unknown => false,
+    };
+    if human_to_move {
+        // Humans reply over this same socket, so let the move prompt read from it
+        return tick_game(game, timelimit, black, white, format, ws_sender, Some(ws_receiver)).await;
+    }
+
+    let tick_fut = tick_game::<R, T>(game, timelimit, black, white, format, ws_sender, None);
     pin_mut!(tick_fut); // black magic right here. Delete this to see a very confusing error
     let mut ws_fut = ws_receiver.next();
     
     loop {
         match select(tick_fut, ws_fut).await {
-            Either::Left((tick_res, ws_fut_continue)) => {
-                ws_fut = ws_fut_continue;
+            Either::Left((tick_res, _)) => {
                 debug!("Standard case");
                 return tick_res;
             },
@@ -247,6 +519,16 @@ async fn tick_game_with_timeout<R: Stream<Item=WSResult<WSMessage>> + StreamExt<
                         debug!("Normal error case");
                         return Err(WSError::ConnectionClosed);
                     },
+                    Some(Ok(msg @ WSMessage::Text(_))) => {
+                        debug!("Out-of-turn message case");
+                        match unwrap_incomming_message(msg) {
+                            // A program is to move, so it's the other side
+                            Ok(ClientMessage::Resign {}) => return Err(resignation(player.opponent())),
+                            Ok(client_msg) => handle_incoming_message(my_id, room_map, client_msg).await,
+                            Err(why) => warn!("Could not parse message from {}: {}", my_id, why),
+                        }
+                        ws_fut = ws_receiver.next();
+                    },
                     Some(Ok(_)) => {
                         // Ignore any other message type 
                         debug!("Ignore case");
@@ -277,25 +559,38 @@ pub async fn play<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Strea
     prq: PlayRequest,
     room_map: RoomMap,
     peer_map: PeerMap,
-    mut ws_stream: T,
+    ws_stream: T,
 ) -> WSResult<()> {
     let my_id = Id::new_v4(); // guaranteed to be unique
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
-    let (black_name, white_name, timelimit) = (prq.black.clone(), prq.white.clone(), prq.t);
+    let (black_name, white_name, timelimit, swap) = (prq.black.clone(), prq.white.clone(), prq.t, prq.swap);
+    // Check the starting position before starting any programs
+    let (game, variant) = match prq.variant().and_then(|variant| Ok((prq.start()?, variant))) {
+        Ok(start) => start,
+        Err(why) => {
+            warn!("{} asked to start from a bad position: {}", &my_id, why);
+            let msg = ServerMessage::GameError {
+                error: format!("Bad starting position: {}", why),
+            };
+            send_ws_message(&mut ws_sender, &msg).await?;
+            return Err(WSError::Io(IOError::new(IOErrorKind::InvalidInput, why.as_str())));
+        },
+    };
+    let format = Notation::new(prq.format, game.board().geometry());
     // need to be mut because a Runner needs to be mut to send messages
     debug!("{} Making black player {}", &my_id, &black_name);
-    let mut black = make_player(&black_name)?; 
+    let mut black = make_player(&black_name, &variant).await?;
     debug!("{} Making white player {}", &my_id, &white_name);
-    let mut white = match make_player(&white_name) {
+    let mut white = match make_player(&white_name, &variant).await {
         Ok(white_player) => white_player,
         Err(why) => {
             debug!("Error starting white player, clean up black just in case");
-            if let (Some(black_err), _) = cleanup(&my_id, &room_map, black, PlayerType::Human).await? {
+            if let (Some(_), _) = cleanup(&my_id, &room_map, &peer_map, black, PlayerType::Human).await? {
                 let msg = ServerMessage::GameError {
                     error: format!("Error starting white player: {}", why)
                 };
-                send_message(&my_id, &room_map, &peer_map, &mut ws_sender, &msg).await?;
+                send_message(&my_id, &room_map, &peer_map, &mut ws_sender, format, &msg).await?;
             }
             return Err(why);
         }
@@ -304,48 +599,92 @@ pub async fn play<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Strea
     // room_map is for Ids that are currently playing games
     debug!("{} Inserting room into map", &my_id);
     room_map.lock().unwrap()
-        .insert(my_id.clone(), prq.to_room(&my_id));
+        .insert(my_id, prq.into_room(game.clone(), variant));
     // peer_map is for Ids that are watching and expect to receive and mirror messages
     // As we are playing, we don't insert ourselves into it
 
+    let ctx = GameContext {
+        id: &my_id,
+        room_map: &room_map,
+        peer_map: &peer_map,
+        black_name,
+        white_name,
+        timelimit,
+        variant,
+        format,
+    };
+
     // start the main play loop
-    let result = play_main(&my_id, &room_map, &peer_map, &mut black, &mut white,
-        black_name, white_name, timelimit, ws_sender, ws_receiver).await;
+    let mut result = play_main(&ctx, &mut black, &mut white, game.clone(), &mut ws_sender, &mut ws_receiver).await;
+
+    if swap && result.as_ref().is_ok_and(|first| worth_a_rematch(first, &black, &white)) {
+        // Same start again with the colors the other way around, so neither
+        // side gets the better half of the opening both times
+        debug!("{} Starting rematch with colors swapped", &my_id);
+        std::mem::swap(&mut black, &mut white);
+        result = play_main(&ctx.swapped(), &mut black, &mut white, game, &mut ws_sender, &mut ws_receiver).await;
+    }
+
+    if let Err(why) = &result {
+        // Let watchers know why their game is about to disappear
+        let msg = ServerMessage::GameError {
+            error: format!("Game ended abnormally: {}", why),
+        };
+        if let Err(send_why) = send_peer_message(&my_id, &room_map, &peer_map, &msg) {
+            warn!("{}", send_why);
+        }
+    }
 
     // Always clean up, no matter if the result is an error or not
     // FIXME: the way it's currently set up, we can't report stderr back to client
-    cleanup(&my_id, &room_map, black, white).await?;
-    return result;
+    cleanup(&my_id, &room_map, &peer_map, black, white).await?;
+    result.map(|_| ())
+}
+
+// Rematches are for games that were played out, or that a program lost by
+// breaking down. A human who resigned or left has had enough
+fn worth_a_rematch(result: &GameResult, black: &PlayerType, white: &PlayerType) -> bool {
+    if result.reason == EndReason::Normal {
+        return true;
+    }
+    match result.outcome.winner() {
+        Player::Black => !is_human(white),
+        Player::White => !is_human(black),
+        Player::Unknown => false,
+    }
 }
 
 // Main loop that does most of the work of playing a game
 // Is not responsible for cleaning up after itself.
-// Takes ownership of stream, so any error reporting visible on the client
-// must be done here
+// Any error reporting visible on the client must be done here
 async fn play_main<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin, T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
-    my_id: &Id,
-    room_map: &RoomMap,
-    peer_map: &PeerMap,
+    ctx: &GameContext<'_>,
     black: &mut PlayerType,
     white: &mut PlayerType,
-    black_name: String,
-    white_name: String,
-    timelimit: f32,
+    mut game: GameRecord,
     mut ws_sender: T,
     mut ws_receiver: R
-) -> WSResult<()> {
-    let mut board = BoardStruct::new();
-    let mut player = Player::Black;
-
-    let msg = 
-        ServerMessage::BoardUpdate {
-            // Initial board message to let client know we have started running
-            board: board.clone(),
-            tomove: player.clone(),
-            black: black_name.clone(),
-            white: white_name.clone()
-        };
-    send_message(my_id, room_map, peer_map, &mut ws_sender, &msg).await?;
+) -> WSResult<GameResult> {
+    let GameContext {id: my_id, room_map, peer_map, variant, format, ..} = *ctx;
+    let mut player = game.tomove();
+
+    // Initial board message to let client know we have started running.
+    // The room might be on its second game, so bring it up to date too
+    let msg = board_update(&game, &ctx.black_name, &ctx.white_name);
+    let (new_game, new_black, new_white) = (game.clone(), ctx.black_name.clone(), ctx.white_name.clone());
+    update_and_send_message(my_id, room_map, peer_map, &mut ws_sender, format,
+        move |room: &mut Room| {
+            room.game = new_game;
+            room.black_name = new_black;
+            room.white_name = new_white;
+        },
+        &msg
+    ).await?;
+    // Custom starting positions can open with a pass
+    if let Some(MoveRecord {player: passer, ..}) = game.last_move() {
+        let msg = ServerMessage::Pass {player: *passer};
+        send_message(my_id, room_map, peer_map, &mut ws_sender, format, &msg).await?;
+    }
 
     loop {
         match player {
@@ -353,18 +692,14 @@ async fn play_main<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult
                 let msg = ServerMessage::GameError {
                     error: "Encoutered unkown player during game! Unrecoverable error".to_string()
                 };
-                send_message(my_id, room_map, peer_map, &mut ws_sender, &msg).await?;
+                send_message(my_id, room_map, peer_map, &mut ws_sender, format, &msg).await?;
                 
                 return Err(WSError::Io(IOError::new(IOErrorKind::InvalidData, format!("Encountered unknown player during game {}", &my_id).as_str())));
             },
             p => {
                 debug!("{} Ticking game", &my_id);
 
-                match tick_game_with_timeout(
-                    &mut board, p, timelimit,
-                    black, white,
-                    &mut ws_sender, &mut ws_receiver
-                ).await {
+                match tick_game_with_timeout(ctx, &mut game, p, black, white, &mut ws_sender, &mut ws_receiver).await {
                     Ok(Some(new_player)) => {
                         player = new_player;
                     },
@@ -372,35 +707,58 @@ async fn play_main<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult
                         // Game has successfully ended
                         break;
                     },
-                    Err(why) => {
+                    Err(why) => match forfeit(&why, &p, black, white) {
+                        Some((loser, reason)) => {
+                            // Somebody broke the rules, ran out of time, or
+                            // left, which ends the game rather than erroring it out
+                            info!("{} {:?} forfeits: {}", &my_id, &loser, why);
+                            let msg = match illegal_move(&why) {
+                                Some(ill) => ServerMessage::from(ill),
+                                None => ServerMessage::GameError {
+                                    error: format!("{:?} forfeits: {}", &loser, why),
+                                },
+                            };
+                            send_message(my_id, room_map, peer_map, &mut ws_sender, format, &msg).await?;
+
+                            let result = GameResult::forfeit(&loser, reason, game.board());
+                            let msg = game_end(&game, result.clone(), Some(why.to_string()));
+                            update_and_send_message(my_id, room_map, peer_map, &mut ws_sender, format,
+                                move |room: &mut Room| room.game = game,
+                                &msg
+                            ).await?;
+                            return Ok(result);
+                        },
                         // Potentially send GameError too
-                        return Err(why);
+                        None => return Err(why),
                     },
                 }
                 // If we successfully get here, that means we know the game
                 // has been ticked and the player updated
-                
-                // That's a lot of clones... ah well
-                let msg = 
-                    ServerMessage::BoardUpdate {
-                        board: board.clone(),
-                        tomove: player.clone(),
-                        black: black_name.clone(),
-                        white: white_name.clone()
-                    };
-                send_message(my_id, room_map, peer_map, &mut ws_sender, &msg).await?;
+                debug!("{} Board is now\n{}", &my_id, game.board());
+
+                let msg = board_update(&game, &ctx.black_name, &ctx.white_name);
+                let new_game = game.clone();
+                update_and_send_message(my_id, room_map, peer_map, &mut ws_sender, format,
+                    move |room: &mut Room| room.game = new_game,
+                    &msg
+                ).await?;
+
+                if let Some(MoveRecord {player: passer, square: None, ..}) = game.last_move() {
+                    let msg = ServerMessage::Pass {player: *passer};
+                    send_message(my_id, room_map, peer_map, &mut ws_sender, format, &msg).await?;
+                }
             }
         }
     }
 
-    let msg = ServerMessage::GameEnd {
-        board: board.clone(),
-        winner: winner(&board),
-        forfeit: false,
-    };
-    send_message(my_id, room_map, peer_map, &mut ws_sender, &msg).await?;
+    let result = GameResult::finished(game.board(), &variant);
+    let msg = game_end(&game, result.clone(), None);
+    update_and_send_message(my_id, room_map, peer_map, &mut ws_sender, format,
+        move |room: &mut Room| room.game = game,
+        &msg
+    ).await?;
     
-    Ok(())
+    Ok(result)
 }
 
 pub async fn watch<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin>(
@@ -410,24 +768,101 @@ pub async fn watch<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Stre
     mut ws_stream: T,
 ) -> WSResult<()> {
     let my_id = Id::new_v4(); // guaranteed to be unique
+    let square_format = wrq.format;
+    let wants_eval = wrq.eval;
+    let watch_id : Id = wrq.into();
+    let (tx, rx) = unbounded();
     // New scope so we don't keep holding on to lock
-    {
+    let snapshot = {
         let mut rooms = room_map.lock().unwrap();
-        let watch_id : Id = wrq.into();
 
         match rooms.get_mut(&watch_id) {
             Some(room) => {
-                room.watching.push(my_id.clone());
-            },
-            None => {
-                // TODO: error somehow
-                warn!("Client {} tried to watch non-existent room {}!", my_id, watch_id);
+                // Registering while holding the room lock means no broadcast
+                // can sneak in between the snapshot and the inserts
+                peer_map.lock().unwrap().insert(my_id, tx);
+                room.watching.push(my_id);
+                Some((room.snapshot(), room.game.board().geometry()))
             },
+            None => None,
+        }
+    };
+
+    if snapshot.is_none() {
+        warn!("Client {} tried to watch non-existent room {}!", my_id, watch_id);
+        let msg = ServerMessage::GameError {
+            error: format!("Room {} does not exist", watch_id),
         };
+        return send_ws_message(&mut ws_stream, &msg).await;
     }
 
-    // TODO: actually start listening for incomming messages and re-sending them
-    Ok(())
+    let (snapshot, geometry) = snapshot.unwrap();
+    let format = Notation::new(square_format, geometry);
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    // Anything broadcast since the snapshot was taken is waiting in rx, so
+    // the watcher sees every move exactly once
+    let result = match send_ws_message(&mut ws_sender, &snapshot.in_format(format)).await {
+        Ok(()) => watch_main(&my_id, rx, format, wants_eval, &mut ws_sender, &mut ws_receiver).await,
+        Err(why) => Err(why),
+    };
+
+    // Always clean up, no matter if the result is an error or not
+    cleanup_watcher(&my_id, &watch_id, &room_map, &peer_map);
+    result
+}
+
+// Forwards everything broadcast to a watcher until the game ends or the
+// watcher goes away
+async fn watch_main<R: Stream<Item=WSResult<WSMessage>> + StreamExt<Item=WSResult<WSMessage>> + Unpin, T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Unpin>(
+    my_id: &Id,
+    mut rx: UnboundedReceiver<ServerMessage>,
+    format: Notation,
+    wants_eval: bool,
+    ws_sender: &mut T,
+    ws_receiver: &mut R,
+) -> WSResult<()> {
+    let mut rx_fut = rx.next();
+    let mut ws_fut = ws_receiver.next();
+
+    loop {
+        match select(rx_fut, ws_fut).await {
+            Either::Left((peer_msg, ws_fut_continue)) => {
+                ws_fut = ws_fut_continue;
+                match peer_msg {
+                    Some(msg) => {
+                        let out = msg.in_format(format);
+                        let out = if wants_eval { out } else { out.without_eval() };
+                        send_ws_message(ws_sender, &out).await?;
+                        // A GameEnd isn't necessarily the last message, since
+                        // swapped rematches happen in the same room. The room
+                        // dropping our sender is what tells us it's over
+                        rx_fut = rx.next();
+                    },
+                    None => {
+                        // The game dropped our sender while cleaning up
+                        debug!("{} saw the game end", my_id);
+                        return Ok(());
+                    },
+                }
+            },
+            Either::Right((ws_res, rx_fut_continue)) => {
+                rx_fut = rx_fut_continue;
+                match ws_res {
+                    Some(Ok(WSMessage::Close(_))) | None => {
+                        debug!("{} stopped watching", my_id);
+                        return Ok(());
+                    },
+                    Some(Ok(_)) => {
+                        // Watchers have nothing to say to us
+                        ws_fut = ws_receiver.next();
+                    },
+                    Some(Err(why)) => {
+                        return Err(why);
+                    },
+                }
+            },
+        }
+    }
 }
 
 
@@ -439,7 +874,7 @@ pub async fn list<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Strea
     let simplified_map : HashMap<Id, ExternalRoom> = 
         room_map.lock().unwrap()
         .iter()
-        .map(|(k, v)| (k.clone(), v.into()))
+        .map(|(k, v)| (*k, v.into()))
         .collect();
     
     send_ws_message(
@@ -450,55 +885,137 @@ pub async fn list<T: Sink<WSMessage, Error=WSError> + SinkExt<WSMessage> + Strea
     // websocket is closed as soon as our handler finishes, nice!
 }
 
+// Works out the picture a RenderRequest asks for
+fn render_svg(rrq: &RenderRequest, room_map: &RoomMap) -> Result<String, String> {
+    let (board, tomove, room_last) = match (&rrq.room, &rrq.board) {
+        (Some(_), Some(_)) => return Err("Give either a board or a room to draw, not both".to_string()),
+        (Some(id), None) => match room_map.lock().unwrap().get(id) {
+            Some(room) => {
+                let last = room.game.moves().iter().rev().find_map(|played| played.square);
+                (*room.game.board(), room.game.tomove(), last)
+            },
+            None => return Err(format!("Room {} does not exist", id)),
+        },
+        (None, Some(board)) => (board.parse::<BoardStruct>().map_err(|why| why.to_string())?, Player::Black, None),
+        (None, None) => (BoardStruct::new(), Player::Black, None),
+    };
+    let format = Notation::new(rrq.format, board.geometry());
+
+    let last_move = match &rrq.last {
+        // Query strings are all text, so numbers have to be picked out by hand
+        Some(last) => Some(format.decode(&match last.parse::<usize>() {
+            Ok(sq) => Square::Number(sq),
+            Err(_) => Square::Name(last.clone()),
+        })?),
+        None => room_last,
+    };
+    let legal_moves = if rrq.legal {
+        legal_moves(rrq.tomove.as_ref().unwrap_or(&tomove), &board)
+    } else {
+        Vec::new()
+    };
+
+    Ok(render::svg(&board, &SvgOptions {
+        last_move,
+        legal_moves,
+        ..SvgOptions::default()
+    }))
+}
+
+// Answers a plain HTTP request for a picture of a board. Unlike the other
+// requests this one never becomes a websocket
+pub async fn render(
+    rrq: RenderRequest,
+    room_map: RoomMap,
+    mut stream: TcpStream,
+) -> std::io::Result<()> {
+    // The request line was already peeked at, but the rest of the head
+    // still has to be read before replying
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || head.len() + read > MAX_HTTP_HEAD {
+            return Err(IOError::new(IOErrorKind::InvalidData, "HTTP request ended early or was too long"));
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    let (status, content_type, body) = match render_svg(&rrq, &room_map) {
+        Ok(svg) => ("200 OK", "image/svg+xml", svg),
+        Err(why) => {
+            warn!("Could not render {:?}: {}", rrq, why);
+            ("400 Bad Request", "text/plain; charset=utf-8", why)
+        },
+    };
+    // Anyone can embed these, so let any page load them
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown(std::net::Shutdown::Both)
+}
+
 fn cleanup_room(
     id: &Id,
     room_map: &RoomMap,
-) -> () {
-    room_map.lock().unwrap().remove(id); 
+    peer_map: &PeerMap,
+) {
+    if let Some(room) = room_map.lock().unwrap().remove(id) {
+        // Dropping the senders lets any remaining watchers know the game is gone
+        let mut peers = peer_map.lock().unwrap();
+        for watcher in room.watching.iter() {
+            peers.remove(watcher);
+        }
+    }
 }
 
-async fn cleanup_runner(
-    mut runner: Runner
-) -> WSResult<String> {
-    match runner::kill_and_get_error(runner).await {
-        Ok(error_out) => {
-            info!("Leftover stderr: {}", &error_out);
-            Ok(error_out)
+fn cleanup_watcher(
+    id: &Id,
+    watch_id: &Id,
+    room_map: &RoomMap,
+    peer_map: &PeerMap,
+) {
+    debug!("{} cleaning up watcher...", id);
+    let mut rooms = room_map.lock().unwrap();
+    if let Some(room) = rooms.get_mut(watch_id) {
+        room.watching.retain(|w| w != id);
+    }
+    peer_map.lock().unwrap().remove(id);
+}
+
+// Stops a player, handing back whatever it had to say
+async fn cleanup_player(
+    player: PlayerType
+) -> WSResult<Option<String>> {
+    match player {
+        PlayerType::Human => Ok(None),
+        PlayerType::Ai(backend) => match backend.stop().await {
+            Ok(error_out) => {
+                info!("Leftover stderr: {}", &error_out);
+                Ok(Some(error_out))
+            },
+            Err(why) => Err(WSError::Io(why))
         },
-        Err(why) => Err(WSError::Io(why))
     }
 }
 
 async fn cleanup(
     id: &Id,
     room_map: &RoomMap,
-    mut black: PlayerType,
-    mut white: PlayerType,
+    peer_map: &PeerMap,
+    black: PlayerType,
+    white: PlayerType,
 ) -> WSResult<(Option<String>, Option<String>)> {
     debug!("{} cleaning up room...", id);
-    cleanup_room(id, room_map);
-
-    match (black, white) {
-        (PlayerType::Ai(black_ai), PlayerType::Ai(white_ai)) => {
-            debug!("Cleaning up both runners...");
-            match futures::future::join(cleanup_runner(black_ai), cleanup_runner(white_ai)).await {
-                (Ok(black_error), Ok(white_error)) => Ok((Some(black_error), Some(white_error))),
-                (Err(black_err), _) => Err(black_err),
-                (_, Err(white_err)) => Err(white_err),
-            }
-        },
-        (PlayerType::Ai(black_ai), PlayerType::Human) => {
-            debug!("Cleaning up just black runner...");
-            Ok((Some(cleanup_runner(black_ai).await?), None))
-        },
-        (PlayerType::Human, PlayerType::Ai(white_ai)) => {
-            debug!("Cleaning up just white runner...");
-            Ok((None, Some(cleanup_runner(white_ai).await?)))
-        },
-        (PlayerType::Human, PlayerType::Human) => {
-            debug!("No runners to clean up!");
-            Ok((None, None))
-        }
+    cleanup_room(id, room_map, peer_map);
+
+    debug!("{} cleaning up players...", id);
+    match futures::future::join(cleanup_player(black), cleanup_player(white)).await {
+        (Ok(black_error), Ok(white_error)) => Ok((black_error, white_error)),
+        (Err(black_err), _) => Err(black_err),
+        (_, Err(white_err)) => Err(white_err),
     }
 }
 
@@ -510,3 +1027,289 @@ fn unwrap_incomming_message(msg: WSMessage) -> WSResult<ClientMessage> {
         Err(error) => Err(WSError::Io(error.into())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use crate::othello::result::Outcome;
+
+    // Both ends of a websocket, with everything the client will say queued
+    // up before the game starts
+    struct MockSocket {
+        incoming: UnboundedReceiver<WSResult<WSMessage>>,
+        outgoing: UnboundedSender<WSMessage>,
+    }
+
+    impl Stream for MockSocket {
+        type Item = WSResult<WSMessage>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.incoming.poll_next_unpin(cx)
+        }
+    }
+
+    impl Sink<WSMessage> for MockSocket {
+        type Error = WSError;
+
+        fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<WSResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, msg: WSMessage) -> WSResult<()> {
+            self.outgoing.unbounded_send(msg).map_err(|_| WSError::ConnectionClosed)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<WSResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<WSResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    // Plays a game between two humans on one socket, who send `replies` in
+    // order and then hang up. Hands back everything the server sent
+    async fn play_human(request: &str, replies: &[ClientMessage]) -> Vec<ServerMessage> {
+        let (client_tx, incoming) = unbounded();
+        let (outgoing, client_rx) = unbounded();
+        for reply in replies {
+            let text = serde_json::to_string(reply).unwrap();
+            client_tx.unbounded_send(Ok(WSMessage::Text(text))).unwrap();
+        }
+        drop(client_tx);
+
+        let prq : PlayRequest = serde_json::from_str(request).unwrap();
+        let room_map = RoomMap::new(Mutex::new(HashMap::new()));
+        let peer_map = PeerMap::new(Mutex::new(HashMap::new()));
+        // The game ending by forfeit still counts as it going fine
+        play(prq, room_map, peer_map, MockSocket {incoming, outgoing}).await.unwrap();
+
+        client_rx
+            .map(|msg| serde_json::from_str(msg.to_text().unwrap()).unwrap())
+            .collect()
+            .await
+    }
+
+    fn both_human(extra: &str) -> String {
+        format!(r#"{{"black": "{0}", "white": "{0}", "t": 5, "format": "algebraic"{1}}}"#, settings::HUMAN_PLAYER, extra)
+    }
+
+    fn move_to(name: &str) -> ClientMessage {
+        ClientMessage::MoveReply {square: Square::Name(name.to_string())}
+    }
+
+    fn name(name: &str) -> Square {
+        Square::Name(name.to_string())
+    }
+
+    fn game_result(sent: &[ServerMessage]) -> (GameResult, Option<String>) {
+        match sent.last() {
+            Some(ServerMessage::GameEnd {result, forfeit_reason, ..}) => (result.clone(), forfeit_reason.clone()),
+            other => panic!("game didn't end with a GameEnd: {:?}", other),
+        }
+    }
+
+    // Black can take h8, after which White is stuck and Black finishes on a1
+    fn passing() -> String {
+        let mut rows = vec![".o@@@@@@"];
+        rows.extend(["@@@@@@@@"; 6].iter());
+        rows.push("@@@@@@o.");
+        format!(r#", "board": "{}""#, rows.concat())
+    }
+
+    #[tokio::test]
+    async fn illegal_moves_are_reported_then_forfeit() {
+        let sent = play_human(&both_human(""), &[move_to("a1"), move_to("d4"), move_to("a1")]).await;
+
+        let reasons : Vec<IllegalMoveReason> = sent.iter()
+            .filter_map(|msg| match msg {
+                ServerMessage::IllegalMove {player: Player::Black, square: Some(_), reason, ..} => Some(*reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasons, vec![IllegalMoveReason::NoBracket, IllegalMoveReason::Occupied, IllegalMoveReason::NoBracket]);
+        assert!(sent.iter().any(|msg| matches!(msg, ServerMessage::IllegalMove {square: Some(sq), ..} if *sq == name("d4"))));
+
+        let (result, forfeit_reason) = game_result(&sent);
+        assert_eq!(result.reason, EndReason::IllegalMove);
+        assert_eq!(result.outcome.winner(), Player::White);
+        assert!(forfeit_reason.is_some());
+    }
+
+    #[tokio::test]
+    async fn resigning_hands_over_the_game() {
+        let sent = play_human(&both_human(""), &[move_to("f5"), ClientMessage::Resign {}]).await;
+        let (result, _) = game_result(&sent);
+        assert_eq!(result.reason, EndReason::Resignation);
+        assert_eq!(result.outcome.winner(), Player::Black);
+        assert_eq!((result.black_discs, result.white_discs), (4, 1));
+    }
+
+    #[tokio::test]
+    async fn hanging_up_is_a_disconnect() {
+        let sent = play_human(&both_human(""), &[]).await;
+        let (result, _) = game_result(&sent);
+        assert_eq!(result.reason, EndReason::Disconnect);
+        assert_eq!(result.outcome.winner(), Player::White);
+    }
+
+    #[tokio::test]
+    async fn passes_and_the_finish() {
+        let sent = play_human(&both_human(&passing()), &[move_to("h8"), move_to("a1")]).await;
+
+        let passes : Vec<&ServerMessage> = sent.iter().filter(|msg| matches!(msg, ServerMessage::Pass {..})).collect();
+        assert!(matches!(passes[..], [ServerMessage::Pass {player: Player::White}]), "{:?}", passes);
+
+        let (result, forfeit_reason) = game_result(&sent);
+        assert_eq!(result, GameResult {
+            outcome: Outcome::BlackWin,
+            reason: EndReason::Normal,
+            black_discs: 64,
+            white_discs: 0,
+        });
+        assert_eq!(forfeit_reason, None);
+    }
+
+    #[tokio::test]
+    async fn custom_starts() {
+        // White to move from the usual start
+        let sent = play_human(&both_human(r#", "tomove": "o""#), &[]).await;
+        match &sent[..2] {
+            [ServerMessage::BoardUpdate {board, tomove: Player::White, ..}, ServerMessage::MoveRequest {tomove: Player::White, squares, ..}] => {
+                assert_eq!(*board, BoardStruct::new());
+                assert_eq!(squares.len(), 4);
+            },
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(game_result(&sent).0.outcome, Outcome::BlackWin);
+
+        // Black's only disc is hemmed in against the edge, so the game opens
+        // with a pass
+        let mut rows = vec!["........"; 3];
+        rows.extend(["ooooooo@", "...oo..o", ".......o", ".......o", ".......o"].iter());
+        let extra = format!(r#", "board": "{}""#, rows.concat());
+        let sent = play_human(&both_human(&extra), &[move_to("h3")]).await;
+        match &sent[..3] {
+            [ServerMessage::BoardUpdate {tomove: Player::White, ..}, ServerMessage::Pass {player: Player::Black}, ServerMessage::MoveRequest {squares, ..}] => {
+                assert_eq!(*squares, vec![name("h3")]);
+            },
+            other => panic!("{:?}", other),
+        }
+        let (result, _) = game_result(&sent);
+        assert_eq!((result.outcome, result.reason), (Outcome::WhiteWin, EndReason::Normal));
+        assert_eq!((result.black_discs, result.white_discs), (0, 15));
+    }
+
+    fn game_ends(sent: &[ServerMessage]) -> usize {
+        sent.iter().filter(|msg| matches!(msg, ServerMessage::GameEnd {..})).count()
+    }
+
+    #[tokio::test]
+    async fn rematches_follow_finished_games() {
+        let extra = format!(r#", "swap": true{}"#, passing());
+        let replies = [move_to("h8"), move_to("a1"), move_to("h8"), move_to("a1")];
+        let sent = play_human(&both_human(&extra), &replies).await;
+        assert_eq!(game_ends(&sent), 2);
+    }
+
+    #[tokio::test]
+    async fn no_rematch_after_resigning() {
+        let extra = r#", "swap": true"#;
+        let sent = play_human(&both_human(extra), &[ClientMessage::Resign {}, move_to("f5")]).await;
+        assert_eq!(game_ends(&sent), 1);
+        assert_eq!(game_result(&sent).0.reason, EndReason::Resignation);
+    }
+
+    #[test]
+    fn rematch_after_a_program_forfeits() {
+        let program = || PlayerType::Ai(runner::make_backend(&"builtin:random".to_string(), &Variant::default()).unwrap());
+        let board = BoardStruct::new();
+        let crashed = GameResult::forfeit(&Player::White, EndReason::Crash, &board);
+        assert!(worth_a_rematch(&crashed, &PlayerType::Human, &program()));
+        let left = GameResult::forfeit(&Player::Black, EndReason::Disconnect, &board);
+        assert!(!worth_a_rematch(&left, &PlayerType::Human, &program()));
+        let drawn = GameResult::finished(&board, &Variant::default());
+        assert!(worth_a_rematch(&drawn, &PlayerType::Human, &PlayerType::Human));
+    }
+
+    #[test]
+    fn programs_can_resign() {
+        let program = || PlayerType::Ai(runner::make_backend(&"builtin:random".to_string(), &Variant::default()).unwrap());
+        // A remote bot giving up on its own turn
+        let gave_up = WSError::Io(IOError::other(Resigned(Player::White)));
+        assert_eq!(forfeit(&gave_up, &Player::White, &PlayerType::Human, &program()), Some((Player::White, EndReason::Resignation)));
+        // The human resigning while it thinks
+        let gave_up = resignation(Player::Black);
+        assert_eq!(forfeit(&gave_up, &Player::White, &PlayerType::Human, &program()), Some((Player::Black, EndReason::Resignation)));
+        // Anything else it does wrong is still a crash
+        let crashed = WSError::Io(IOError::other("Remote bot gave up"));
+        assert_eq!(forfeit(&crashed, &Player::White, &PlayerType::Human, &program()), Some((Player::White, EndReason::Crash)));
+    }
+
+    // What the server says to a picture request for `target`
+    async fn render_response(target: &str) -> String {
+        let mut listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target);
+        client.write_all(request.as_bytes()).await.unwrap();
+        let rrq = match crate::protocol::urls::parse_uri(target.parse().unwrap()) {
+            Ok(ClientRequest::Render(rrq)) => rrq,
+            other => panic!("{:?}", other),
+        };
+        render(rrq, RoomMap::new(Mutex::new(HashMap::new())), server).await.unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn render_requests() {
+        let response = render_response("/render?legal=true&last=f5&format=algebraic").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: image/svg+xml\r\n"));
+        assert!(response.ends_with("</svg>"));
+
+        let response = render_response("/render?board=nonsense").await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", response);
+        assert!(response.ends_with("board has 8 squares, expected 64/100/144 with the border or 36/64/100 without"), "{}", response);
+
+        let response = render_response("/render?last=z9").await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn updates_carry_the_move() {
+        let sent = play_human(&both_human(&passing()), &[move_to("h8"), move_to("a1")]).await;
+
+        let updates : Vec<&ServerMessage> = sent.iter().filter(|msg| matches!(msg, ServerMessage::BoardUpdate {..})).collect();
+        // The last move goes out in the GameEnd instead
+        assert_eq!(updates.len(), 2);
+        match updates[0] {
+            ServerMessage::BoardUpdate {last_move, legal_moves, ..} => {
+                assert!(last_move.is_none());
+                assert_eq!(*legal_moves, vec![name("a1"), name("h8")]);
+            },
+            _ => unreachable!(),
+        }
+        match updates[1] {
+            ServerMessage::BoardUpdate {tomove, last_move: Some(played), legal_moves, black_discs, white_discs, eval, ..} => {
+                assert_eq!((played.player, played.square.clone()), (Player::Black, Some(name("h8"))));
+                assert_eq!(played.flipped, vec![name("g8")]);
+                // White had to pass, so it's Black again
+                assert_eq!(*tomove, Player::Black);
+                assert_eq!(*legal_moves, vec![name("a1")]);
+                assert_eq!((*black_discs, *white_discs), (62, 1));
+                // Players don't get hints
+                assert!(eval.is_none());
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+// The game logic on its own, for analysis tools and anything else that
+// wants to play Othello without running the server
+pub mod othello;
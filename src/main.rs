@@ -4,10 +4,8 @@ use std::{
     sync::{Mutex},
     env,
 };
+use tokio::time::{delay_for, Duration};
 use log::*;
-use serde_json::{
-    error::Error as SerdeError
-};
 use tokio::net::{
     TcpListener,
     TcpStream
@@ -17,13 +15,13 @@ use tokio_tungstenite::{
     tungstenite::Error,
 };
 use tungstenite::{
-    Message as WSMessage,
     Result as WSResult,
     error::Error as WSError,
 };
 
+// Shared with the library
+use tungstenite_testings::othello;
 // Private modules
-mod othello;
 mod protocol;
 mod runner;
 mod handlers;
@@ -31,16 +29,46 @@ use crate::protocol::*;
 use handlers::PeerMap;
 
 async fn accept_connection(room_map: RoomMap, peer_map: PeerMap, addr: SocketAddr, stream: TcpStream) {
-    if let Err(e) = handle_connection(room_map, peer_map, addr, stream).await {
-        match e {
-            // uncomment below line once done debugging
-            //Error::ConnectionClosed | Error::AlreadyClosed => (),
-            err => error!("Error processing connection: {:?}", err),
+    if let Err(err) = handle_connection(room_map, peer_map, addr, stream).await {
+        // Once done debugging, Error::ConnectionClosed and
+        // Error::AlreadyClosed shouldn't need logging
+        error!("Error processing connection: {:?}", err);
+    }
+}
+
+// Number of times to look for a whole request line before giving up on it
+const PEEK_TRIES : usize = 5;
+
+// Reads the target of an HTTP request off the front of the stream without
+// taking it, so a websocket handshake can still go ahead afterwards
+async fn peek_request_target(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = [0u8; 2048];
+    for _ in 0..PEEK_TRIES {
+        let read = stream.peek(&mut buf).await.ok()?;
+        if let Some(end) = buf[..read].windows(2).position(|w| w == b"\r\n") {
+            // "GET /render?board=... HTTP/1.1"
+            let line = std::str::from_utf8(&buf[..end]).ok()?;
+            return line.split_whitespace().nth(1).map(|target| target.to_string());
+        }
+        if read == 0 || read == buf.len() {
+            return None;
         }
+        delay_for(Duration::from_millis(10)).await;
     }
+    None
 }
 
-async fn handle_connection(room_map: RoomMap, peer_map: PeerMap, addr: SocketAddr, stream: TcpStream) -> WSResult<()> {
+// The handshake callback's error is a whole HTTP response, which is the
+// shape tungstenite wants it in
+#[allow(clippy::result_large_err)]
+async fn handle_connection(room_map: RoomMap, peer_map: PeerMap, addr: SocketAddr, mut stream: TcpStream) -> WSResult<()> {
+    // Pictures are plain HTTP, everything else is a websocket
+    let target = peek_request_target(&mut stream).await.and_then(|target| target.parse::<http::Uri>().ok());
+    if let Some(Ok(ClientRequest::Render(rrq))) = target.map(protocol::urls::parse_uri) {
+        debug!("{} asked for a picture", addr);
+        return handlers::render(rrq, room_map, stream).await.map_err(WSError::Io);
+    }
+
     let mut request_type: Option<ClientRequest> = None;
 
     let ws_stream = accept_hdr_async(
@@ -75,6 +103,9 @@ async fn handle_connection(room_map: RoomMap, peer_map: PeerMap, addr: SocketAdd
         Some(ClientRequest::List(lrq)) => {
             handlers::list(lrq, room_map, ws_stream).await
         },
+        Some(ClientRequest::Render(_)) => {
+            Err(Error::Protocol(std::borrow::Cow::from("Board pictures are plain HTTP, not websockets")))
+        },
         None => {
             // TODO: error somehow b/c this shouldn't be possible
             Err(Error::Protocol(std::borrow::Cow::from("Something went wrong; failed to parse request type but fell through anyways")))
@@ -86,6 +117,13 @@ async fn handle_connection(room_map: RoomMap, peer_map: PeerMap, addr: SocketAdd
 async fn main() {
     simple_logger::init_with_level(log::Level::Debug).unwrap();
 
+    if env::var_os(runner::settings::UNJAILED_ENV).is_some() {
+        warn!("{} is set, AIs will run with the server's privileges!", runner::settings::UNJAILED_ENV);
+    } else if let Err(why) = runner::settings::check_jail() {
+        error!("Can't run AIs safely: {}", why);
+        std::process::exit(1);
+    }
+
     let watchers = PeerMap::new(Mutex::new(HashMap::new()));
     let players = RoomMap::new(Mutex::new(HashMap::new()));
 
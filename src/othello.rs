@@ -23,7 +23,7 @@ pub enum Player {
 }
 
 impl Player {
-    fn opponent (&self) -> Player {
+    pub fn opponent (&self) -> Player {
         match self {
             Player::Black => Player::White,
             Player::White => Player::Black,
@@ -32,15 +32,6 @@ impl Player {
     }
 }
 
-pub const UP    : i32 = -10;
-pub const DOWN  : i32 = 10;
-pub const LEFT  : i32 = -1;
-pub const RIGHT : i32 = 1;
-pub const UP_RIGHT   : i32 = UP + RIGHT;
-pub const UP_LEFT    : i32 = UP + LEFT;
-pub const DOWN_RIGHT : i32 = DOWN + RIGHT;
-pub const DOWN_LEFT  : i32 = DOWN + LEFT;
-
 pub enum Direction {
     Up,
     Down,
@@ -52,27 +43,19 @@ pub enum Direction {
     DownLeft,
 }
 
-const LEGAL_SPACES : [usize; 64] = [
-    11, 12, 13, 14, 15, 16, 17, 18, 
-    21, 22, 23, 24, 25, 26, 27, 28, 
-    31, 32, 33, 34, 35, 36, 37, 38, 
-    41, 42, 43, 44, 45, 46, 47, 48, 
-    51, 52, 53, 54, 55, 56, 57, 58, 
-    61, 62, 63, 64, 65, 66, 67, 68, 
-    71, 72, 73, 74, 75, 76, 77, 78, 
-    81, 82, 83, 84, 85, 86, 87, 88];
-
 impl Direction {
-    pub fn value(&self) -> i32 {
+    // How far apart neighbouring squares are in a mailbox `width` squares
+    // across. On the standard board that's 10
+    pub fn offset(&self, width: i32) -> i32 {
         match self {
-            Direction::Up => UP,
-            Direction::Down => DOWN,
-            Direction::Left => LEFT,
-            Direction::Right => RIGHT,
-            Direction::UpRight => UP_RIGHT,
-            Direction::UpLeft => UP_LEFT,
-            Direction::DownRight => DOWN_RIGHT,
-            Direction::DownLeft => DOWN_LEFT,
+            Direction::Up => -width,
+            Direction::Down => width,
+            Direction::Left => -1,
+            Direction::Right => 1,
+            Direction::UpRight => 1 - width,
+            Direction::UpLeft => -1 - width,
+            Direction::DownRight => width + 1,
+            Direction::DownLeft => width - 1,
        }
     }
 
@@ -84,57 +67,193 @@ impl Direction {
     }
 }
 
-pub type Board = [Piece; 100];
-
-fn initial_board () -> Board {
-    let mut board = [Piece::OUTER; 100];
-    for x in 1..9 {
-        for y in 1..9 {
-            board[x+y*10] = Piece::EMPTY;
-       }
-    }
-    board[44] = Piece::WHITE;
-    board[45] = Piece::BLACK;
-    board[54] = Piece::BLACK;
-    board[55] = Piece::WHITE;
+// A board in TJHSST notation, bordered by OUTER on every side. The standard
+// board is 10x10 with the 8x8 playing area inside
+pub type Board = Vec<Piece>;
 
-    board
-}
-// I can't believe they can't contantize the previous function :(
-pub const INITIAL_BOARD : Board = 
-[
-Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::WHITE, Piece::BLACK, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::BLACK, Piece::WHITE, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::EMPTY, Piece::OUTER,
-Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER, Piece::OUTER,
-];
-
-
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq)]
 pub struct BoardStruct {
-    board: Board,
+    black: Bitboard,
+    white: Bitboard,
+    // Zobrist hash of the discs, kept up to date as the board changes
+    hash: u64,
+    geometry: &'static Geometry,
+}
+
+impl Default for BoardStruct {
+    fn default() -> Self {
+        BoardStruct::new()
+    }
 }
 
 impl BoardStruct {
+    // The standard 8x8 starting position
     pub fn new() -> Self {
+        BoardStruct::initial(&bitboard::STANDARD)
+    }
+
+    // Four discs in the middle, white on the top left one
+    pub fn initial(geometry: &'static Geometry) -> Self {
+        let [top_left, top_right, bottom_left, bottom_right] = center(geometry);
+        BoardStruct::from_bitboards(
+            geometry,
+            geometry.square_mask(top_right) | geometry.square_mask(bottom_left),
+            geometry.square_mask(top_left) | geometry.square_mask(bottom_right),
+        )
+    }
+
+    pub fn from_bitboards(geometry: &'static Geometry, black: Bitboard, white: Bitboard) -> Self {
+        let black = black & geometry.all;
+        let white = white & geometry.all & !black;
         BoardStruct {
-            board: INITIAL_BOARD.clone(),
+            black,
+            white,
+            hash: hash::hash_discs(black, white),
+            geometry,
+        }
+    }
+
+    // Squares outside the playing area are ignored
+    pub fn from_mailbox(geometry: &'static Geometry, board: &[Piece]) -> Self {
+        let mut bs = BoardStruct::from_bitboards(geometry, 0, 0);
+        for sq in geometry.legal_spaces() {
+            if let Some(piece) = board.get(sq) {
+                bs.set(sq, *piece);
+            }
+        }
+        bs
+    }
+
+    pub fn to_mailbox(&self) -> Board {
+        let width = self.geometry.width();
+        let mut board = vec![Piece::OUTER; width * width];
+        for sq in self.geometry.legal_spaces() {
+            board[sq] = self.get(sq);
         }
+        board
+    }
+
+    pub fn geometry(&self) -> &'static Geometry {
+        self.geometry
     }
+
+    pub fn size(&self) -> usize {
+        self.geometry.size
+    }
+
+    pub fn get(&self, square: moves::Loc) -> Piece {
+        let mask = self.geometry.square_mask(square);
+        if mask == 0 {
+            Piece::OUTER
+        } else if self.black & mask != 0 {
+            Piece::BLACK
+        } else if self.white & mask != 0 {
+            Piece::WHITE
+        } else {
+            Piece::EMPTY
+        }
+    }
+
+    // Squares off the board are left alone, and so are attempts to put
+    // OUTER on the board
+    pub fn set(&mut self, square: moves::Loc, piece: Piece) {
+        let mask = self.geometry.square_mask(square);
+        self.black &= !mask;
+        self.white &= !mask;
+        match piece {
+            Piece::BLACK => self.black |= mask,
+            Piece::WHITE => self.white |= mask,
+            Piece::EMPTY | Piece::OUTER => (),
+        }
+        self.hash = hash::hash_discs(self.black, self.white);
+    }
+
+    // The discs of `player` and of their opponent, in that order
+    pub fn bitboards(&self, player: &Player) -> (Bitboard, Bitboard) {
+        match player {
+            Player::Black => (self.black, self.white),
+            Player::White => (self.white, self.black),
+            Player::Unknown => (0, 0),
+        }
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Places (or removes) a disc for `player` and flips `flipped` over to
+    // them (or back). Doing this twice leaves the board as it was, so it
+    // both makes and unmakes moves
+    pub fn toggle_move(&mut self, player: &Player, placed: Bitboard, flipped: Bitboard) {
+        let (own, opp) = self.bitboards(player);
+        let (own, opp) = (own ^ (placed | flipped), opp ^ flipped);
+        self.hash ^= hash::disc_keys(player, placed | flipped) ^ hash::disc_keys(&player.opponent(), flipped);
+        match player {
+            Player::Black => { self.black = own; self.white = opp; },
+            Player::White => { self.white = own; self.black = opp; },
+            Player::Unknown => (),
+        }
+    }
+}
+
+// The middle four squares, which always have the first discs on them:
+// top left, top right, bottom left, bottom right
+pub fn center(geometry: &Geometry) -> [moves::Loc; 4] {
+    let (width, half) = (geometry.width(), geometry.size / 2);
+    let top_left = half * width + half;
+    [top_left, top_left + 1, top_left + width, top_left + width + 1]
 }
 // whyy
 impl std::fmt::Debug for BoardStruct {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(&&self.board[..], f)
+        std::fmt::Debug::fmt(&&self.to_mailbox()[..], f)
+    }
+}
+
+// A grid with algebraic labels, one row per line:
+//   a b c d e f g h
+// 1 . . . . . . . .
+// ...
+// 4 . . . o @ . . .
+impl std::fmt::Display for BoardStruct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = self.size();
+        let label_width = size.to_string().len();
+        write!(f, "{:w$}", "", w = label_width)?;
+        for col in 0..size {
+            write!(f, " {}", (b'a' + col as u8) as char)?;
+        }
+        for row in 0..size {
+            write!(f, "\n{:>w$}", row + 1, w = label_width)?;
+            for col in 0..size {
+                let sq = (row + 1) * self.geometry.width() + col + 1;
+                write!(f, " {}", char::from(self.get(sq)))?;
+            }
+        }
+        Ok(())
     }
 }
 
 mod serialization;
 pub mod moves;
+pub mod bitboard;
+pub mod record;
+pub mod ai;
+pub mod endgame;
+pub mod notation;
+pub mod ggf;
+pub mod wthor;
+pub mod hash;
+pub mod symmetry;
+pub mod openings;
+pub mod eval;
+pub mod result;
+pub mod xot;
+pub mod variant;
+pub mod render;
+pub mod perft;
 mod conversions;
+
+pub use conversions::{PieceParseError, BoardParseError};
+
+use bitboard::{Bitboard, Geometry};
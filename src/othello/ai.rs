@@ -0,0 +1,236 @@
+// Built-in computer players, so games can run without any python around
+use rand::seq::SliceRandom;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::othello::*;
+use crate::othello::bitboard::{Bits, Geometry};
+use crate::othello::endgame;
+use crate::othello::eval;
+use crate::othello::moves::Loc;
+
+pub const DEFAULT_DEPTH : u32 = 4;
+pub const MAX_DEPTH : u32 = 10;
+// With this few empty squares, alphabeta just plays perfectly instead
+pub const ENDGAME_EMPTIES : u32 = 12;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    // Any legal move at all
+    Random,
+    // Whatever flips the most discs right now
+    Greedy,
+    // Whatever leaves the best looking board by square weights
+    Positional,
+    // Minimax with alpha-beta pruning to a fixed depth
+    AlphaBeta(u32),
+}
+
+// Classic square weights for the standard board, indexed by bit. Corners
+// are great, the squares next to them are terrible
+const WEIGHTS : [i32; 64] = [
+    100, -20,  10,   5,   5,  10, -20, 100,
+    -20, -50,  -2,  -2,  -2,  -2, -50, -20,
+     10,  -2,  -1,  -1,  -1,  -1,  -2,  10,
+      5,  -2,  -1,  -1,  -1,  -1,  -2,   5,
+      5,  -2,  -1,  -1,  -1,  -1,  -2,   5,
+     10,  -2,  -1,  -1,  -1,  -1,  -2,  10,
+    -20, -50,  -2,  -2,  -2,  -2, -50, -20,
+    100, -20,  10,   5,   5,  10, -20, 100,
+];
+
+// Bigger than any heuristic score, so finished games always win out
+const WIN_SCORE : i32 = 1_000_000;
+
+impl Strategy {
+    // Picks a move for `player`, or None if they have to pass. With `anti`
+    // set, it tries to end up with as few discs as it can. Setting `stop`
+    // cuts the search short, after which the move is legal but no good
+    pub fn choose_move(&self, board: &BoardStruct, player: &Player, anti: bool, stop: &AtomicBool) -> Option<Loc> {
+        let search = Search {g: board.geometry(), anti, stop};
+        let (own, opp) = board.bitboards(player);
+        if search.g.fits_u64() {
+            self.choose(&search, board, player, u64::narrow(own), u64::narrow(opp))
+        } else {
+            self.choose(&search, board, player, own, opp)
+        }
+    }
+
+    fn choose<B: Bits>(&self, search: &Search, board: &BoardStruct, player: &Player, own: B, opp: B) -> Option<Loc> {
+        let g = search.g;
+        let moves = bits(g.legal_moves(own, opp));
+        if moves.is_empty() {
+            return None;
+        }
+
+        let best = match self {
+            Strategy::Random => *moves.choose(&mut rand::thread_rng())?,
+            Strategy::Greedy => *moves
+                .iter()
+                .max_by_key(|bit| search.sign() * g.flips(own, opp, B::bit(**bit)).count_ones() as i32)?,
+            Strategy::Positional => *moves
+                .iter()
+                .max_by_key(|bit| {
+                    let (new_own, new_opp) = search.play(own, opp, **bit);
+                    search.sign() * search.weighted_score(new_own, new_opp)
+                })?,
+            Strategy::AlphaBeta(depth) => {
+                if g.area() - (own | opp).count_ones() <= ENDGAME_EMPTIES {
+                    // If it's stopped first, the search below stops straight
+                    // away too and falls back on the best looking square
+                    if let Some(solution) = endgame::solve_until(board, player, search.anti, search.stop) {
+                        return solution.best_move;
+                    }
+                }
+                search.deepen(own, opp, moves, *depth)
+            },
+        };
+        Some(g.bit_to_square(best))
+    }
+}
+
+// Reads names like "random" or "alphabeta:6"
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let arg = parts.next();
+
+        match (name, arg) {
+            ("random", None) => Ok(Strategy::Random),
+            ("greedy", None) => Ok(Strategy::Greedy),
+            ("positional", None) => Ok(Strategy::Positional),
+            ("alphabeta", None) => Ok(Strategy::AlphaBeta(DEFAULT_DEPTH)),
+            ("alphabeta", Some(depth)) => match depth.parse::<u32>() {
+                Ok(d) if (1..=MAX_DEPTH).contains(&d) => Ok(Strategy::AlphaBeta(d)),
+                _ => Err(format!("alphabeta depth must be a number from 1 to {}, not \"{}\"", MAX_DEPTH, depth)),
+            },
+            ("random", Some(_)) | ("greedy", Some(_)) | ("positional", Some(_)) => {
+                Err(format!("builtin player {} doesn't take any options", name))
+            },
+            (name, _) => Err(format!("unknown builtin player \"{}\"", name)),
+        }
+    }
+}
+
+fn bits<B: Bits>(mut b: B) -> Vec<u32> {
+    let mut out = Vec::with_capacity(b.count_ones() as usize);
+    while b != B::ZERO {
+        out.push(b.trailing_zeros());
+        b &= b - B::ONE;
+    }
+    out
+}
+
+// What stays the same through a whole search
+struct Search<'a> {
+    g: &'static Geometry,
+    anti: bool,
+    // Checked at every node, so a search nobody is waiting on ends quickly
+    stop: &'a AtomicBool,
+}
+
+impl Search<'_> {
+    // Scores are worked out as if more discs were better, then flipped for
+    // anti-reversi
+    fn sign(&self) -> i32 {
+        if self.anti { -1 } else { 1 }
+    }
+
+    fn play<B: Bits>(&self, own: B, opp: B, bit: u32) -> (B, B) {
+        let placed = B::bit(bit);
+        let flipped = self.g.flips(own, opp, placed);
+        (own | placed | flipped, opp & !flipped)
+    }
+
+    // Other board sizes get weights in the same spirit as the table
+    fn weight(&self, bit: u32) -> i32 {
+        if self.g.size == 8 {
+            return WEIGHTS[bit as usize];
+        }
+        let (size, bit) = (self.g.size, bit as usize);
+        let (row, col) = (bit / size, bit % size);
+        let edge = |x: usize| x == 0 || x == size - 1;
+        let near = |x: usize| x == 1 || x == size - 2;
+        match (edge(row), edge(col), near(row), near(col)) {
+            (true, true, _, _) => 100,
+            (true, _, _, true) | (_, true, true, _) => -20,
+            (_, _, true, true) => -50,
+            (true, _, _, _) | (_, true, _, _) => 5,
+            (_, _, true, _) | (_, _, _, true) => -2,
+            _ => -1,
+        }
+    }
+
+    // Best squares first, which makes pruning much more effective
+    fn order_moves(&self, mut moves: Vec<u32>) -> Vec<u32> {
+        moves.sort_by_key(|bit| -self.sign() * self.weight(*bit));
+        moves
+    }
+
+    fn weighted_score<B: Bits>(&self, own: B, opp: B) -> i32 {
+        bits(own).iter().map(|b| self.weight(*b)).sum::<i32>()
+            - bits(opp).iter().map(|b| self.weight(*b)).sum::<i32>()
+    }
+
+    // Searches `moves` one ply deeper at a time, up to `max_depth`, so that
+    // once it's stopped there's the best move of the last depth it finished.
+    // Before the first one finishes, that's the best looking square
+    fn deepen<B: Bits>(&self, own: B, opp: B, moves: Vec<u32>, max_depth: u32) -> u32 {
+        let mut moves = self.order_moves(moves);
+        let mut best = moves[0];
+        for depth in 1..=max_depth {
+            let mut alpha = -WIN_SCORE - 100;
+            let mut best_here = best;
+            for bit in &moves {
+                let (new_own, new_opp) = self.play(own, opp, *bit);
+                let score = -self.alphabeta(new_opp, new_own, depth - 1, -WIN_SCORE - 100, -alpha, false);
+                if score > alpha {
+                    alpha = score;
+                    best_here = *bit;
+                }
+            }
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            best = best_here;
+            // Trying the last best move first makes the next depth prune more
+            let at = moves.iter().position(|bit| *bit == best).unwrap_or(0);
+            moves[..=at].rotate_right(1);
+        }
+        best
+    }
+
+    // Negamax from the point of view of `own`, who is to move
+    fn alphabeta<B: Bits>(&self, own: B, opp: B, depth: u32, mut alpha: i32, beta: i32, passed: bool) -> i32 {
+        if self.stop.load(Ordering::Relaxed) {
+            return 0;
+        }
+        let moves = self.g.legal_moves(own, opp);
+        if moves == B::ZERO {
+            if passed {
+                // Neither side can move, so the game is over
+                let diff = self.sign() * (own.count_ones() as i32 - opp.count_ones() as i32);
+                return diff.signum() * WIN_SCORE + diff;
+            }
+            return -self.alphabeta(opp, own, depth, -beta, -alpha, true);
+        }
+        if depth == 0 {
+            return self.sign() * eval::heuristic(self.g, own, opp);
+        }
+
+        for bit in self.order_moves(bits(moves)) {
+            let (new_own, new_opp) = self.play(own, opp, bit);
+            let score = -self.alphabeta(new_opp, new_own, depth - 1, -beta, -alpha, false);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+}
@@ -0,0 +1,216 @@
+// Shift-based move generation, one bit per square. Bit 0 is the top left
+// square and bits go across each row before moving down, so bit order
+// matches square order. A board of any size uses the low size * size bits,
+// which on the standard board is the usual 8x8 layout.
+//
+// A board is a pair of bitboards, one per color. They were u64s until 10x10
+// boards came along, which need 100 bits, so they're stored as u128s now.
+// Everything that works on the bits is generic over Bits, though, and boards
+// up to 8x8 are narrowed to u64s for search and move generation, which
+// shift in half the instructions.
+//
+// Squares are numbered TJHSST style, as an index into a mailbox with a one
+// square border around the board: 11..88 on the standard board
+use std::fmt::Debug;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not, Shl, Shr, Sub};
+
+use crate::othello::Direction;
+use crate::othello::moves::Loc;
+
+// Wide enough for any board
+pub type Bitboard = u128;
+
+// The integers a board's bits can be worked on in
+pub trait Bits: Copy + Debug + Eq + Ord
+    + BitAnd<Output=Self> + BitOr<Output=Self> + BitXor<Output=Self> + Not<Output=Self> + Sub<Output=Self>
+    + Shl<usize, Output=Self> + Shr<usize, Output=Self> + BitAndAssign + BitOrAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+    // Drops the bits that don't fit, which are never on a board this fits
+    fn narrow(b: Bitboard) -> Self;
+    fn widen(self) -> Bitboard;
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    fn wrapping_neg(self) -> Self;
+
+    fn bit(n: u32) -> Self {
+        Self::ONE << n as usize
+    }
+}
+
+macro_rules! impl_bits {
+    ($t:ty) => {
+        impl Bits for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            fn narrow(b: Bitboard) -> Self { b as $t }
+            fn widen(self) -> Bitboard { self as Bitboard }
+            fn count_ones(self) -> u32 { <$t>::count_ones(self) }
+            fn trailing_zeros(self) -> u32 { <$t>::trailing_zeros(self) }
+            fn wrapping_neg(self) -> Self { <$t>::wrapping_neg(self) }
+        }
+    };
+}
+
+impl_bits!(u64);
+impl_bits!(u128);
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Geometry {
+    pub size: usize,
+    // Every square on the board
+    pub all: Bitboard,
+    // Masks used to stop shifts from wrapping around the edges of the board
+    not_left_column: Bitboard,
+    not_right_column: Bitboard,
+}
+
+const fn geometry (size: usize) -> Geometry {
+    let all : Bitboard = (1 << (size * size)) - 1;
+    let mut left : Bitboard = 0;
+    let mut right : Bitboard = 0;
+    let mut row = 0;
+    while row < size {
+        left |= 1 << (row * size);
+        right |= 1 << (row * size + size - 1);
+        row += 1;
+    }
+    Geometry {
+        size,
+        all,
+        not_left_column: all & !left,
+        not_right_column: all & !right,
+    }
+}
+
+pub static SMALL : Geometry = geometry(6);
+pub static STANDARD : Geometry = geometry(8);
+pub static LARGE : Geometry = geometry(10);
+
+pub const SIZES : [usize; 3] = [6, 8, 10];
+
+impl Geometry {
+    pub fn for_size (size: usize) -> Option<&'static Geometry> {
+        match size {
+            6 => Some(&SMALL),
+            8 => Some(&STANDARD),
+            10 => Some(&LARGE),
+            _ => None,
+        }
+    }
+
+    // Number of squares on the board
+    pub fn area (&self) -> u32 {
+        (self.size * self.size) as u32
+    }
+
+    // Side length of the mailbox, border included
+    pub fn width (&self) -> usize {
+        self.size + 2
+    }
+
+    // Whether the board's bits fit in a u64
+    pub fn fits_u64 (&self) -> bool {
+        self.area() <= 64
+    }
+
+    pub fn all_bits<B: Bits> (&self) -> B { B::narrow(self.all) }
+    fn not_left<B: Bits> (&self) -> B { B::narrow(self.not_left_column) }
+    fn not_right<B: Bits> (&self) -> B { B::narrow(self.not_right_column) }
+
+    pub fn shift_up<B: Bits> (&self, b: B) -> B { b >> self.size }
+    pub fn shift_down<B: Bits> (&self, b: B) -> B { (b << self.size) & self.all_bits() }
+    pub fn shift_left<B: Bits> (&self, b: B) -> B { (b >> 1) & self.not_right() }
+    pub fn shift_right<B: Bits> (&self, b: B) -> B { (b << 1) & self.not_left() }
+    pub fn shift_up_left<B: Bits> (&self, b: B) -> B { (b >> (self.size + 1)) & self.not_right() }
+    pub fn shift_up_right<B: Bits> (&self, b: B) -> B { (b >> (self.size - 1)) & self.not_left() }
+    pub fn shift_down_left<B: Bits> (&self, b: B) -> B { (b << (self.size - 1)) & self.not_right() }
+    pub fn shift_down_right<B: Bits> (&self, b: B) -> B { (b << (self.size + 1)) & self.not_left() }
+
+    pub fn shift<B: Bits> (&self, direction: &Direction, b: B) -> B {
+        match direction {
+            Direction::Up => self.shift_up(b),
+            Direction::Down => self.shift_down(b),
+            Direction::Left => self.shift_left(b),
+            Direction::Right => self.shift_right(b),
+            Direction::UpRight => self.shift_up_right(b),
+            Direction::UpLeft => self.shift_up_left(b),
+            Direction::DownRight => self.shift_down_right(b),
+            Direction::DownLeft => self.shift_down_left(b),
+        }
+    }
+
+    // Converts a square in the mailbox to its bit, if it is on the board
+    pub fn square_to_bit (&self, square: Loc) -> Option<u32> {
+        let width = self.width();
+        let (row, col) = (square / width, square % width);
+        if square < width * width && row >= 1 && row <= self.size && col >= 1 && col <= self.size {
+            Some(((row - 1) * self.size + (col - 1)) as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn bit_to_square (&self, bit: u32) -> Loc {
+        let (row, col) = (bit as usize / self.size, bit as usize % self.size);
+        (row + 1) * self.width() + (col + 1)
+    }
+
+    pub fn square_mask (&self, square: Loc) -> Bitboard {
+        match self.square_to_bit(square) {
+            Some(bit) => 1 << bit,
+            None => 0,
+        }
+    }
+
+    // Iterates over the squares of every set bit, lowest first
+    pub fn squares<B: Bits> (&'static self, mut b: B) -> impl Iterator<Item=Loc> {
+        std::iter::from_fn(move || {
+            if b == B::ZERO {
+                None
+            } else {
+                let bit = b.trailing_zeros();
+                b &= b - B::ONE;
+                Some(self.bit_to_square(bit))
+            }
+        })
+    }
+
+    // Every square on the board, in order
+    pub fn legal_spaces (&'static self) -> impl Iterator<Item=Loc> {
+        self.squares(self.all)
+    }
+
+    // Every empty square that brackets at least one of `opp`'s discs
+    pub fn legal_moves<B: Bits> (&self, own: B, opp: B) -> B {
+        let empty = self.all_bits::<B>() & !(own | opp);
+        let mut moves = B::ZERO;
+        for d in Direction::iter() {
+            // A line of opponent discs is at most size - 2 long
+            let mut line = self.shift(d, own) & opp;
+            for _ in 0..self.size - 3 {
+                line |= self.shift(d, line) & opp;
+            }
+            moves |= self.shift(d, line) & empty;
+        }
+        moves
+    }
+
+    // The discs that `own` flips by playing on `placed`, which must be a single bit
+    pub fn flips<B: Bits> (&self, own: B, opp: B, placed: B) -> B {
+        let mut flipped = B::ZERO;
+        for d in Direction::iter() {
+            let mut line = B::ZERO;
+            let mut cursor = self.shift(d, placed);
+            while cursor & opp != B::ZERO {
+                line |= cursor;
+                cursor = self.shift(d, cursor);
+            }
+            if cursor & own != B::ZERO {
+                flipped |= line;
+            }
+        }
+        flipped
+    }
+}
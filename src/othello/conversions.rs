@@ -1,5 +1,62 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
 use crate::othello::*;
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PieceParseError(pub char);
+
+impl fmt::Display for PieceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is not a piece, expected one of '@', 'o', '.' or '?'", self.0)
+    }
+}
+
+impl Error for PieceParseError {}
+
+// Everything that can be wrong with a board in TJHSST notation. Indices are
+// into the mailbox, border included
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BoardParseError {
+    WrongLength(usize),
+    // A fine board, just not the size that was asked for
+    WrongSize {found: usize, expected: usize},
+    BadPiece {index: usize, found: char},
+    // Something other than '?' in the outer ring
+    BadBorder {index: usize, found: Piece},
+    // A '?' on one of the playable squares
+    BadInterior {index: usize},
+}
+
+impl fmt::Display for BoardParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardParseError::WrongLength(len) => {
+                let lengths = |border: usize| -> Vec<String> {
+                    bitboard::SIZES
+                        .iter()
+                        .map(|size| ((size + border) * (size + border)).to_string())
+                        .collect()
+                };
+                write!(f, "board has {} squares, expected {} with the border or {} without",
+                    len, lengths(2).join("/"), lengths(0).join("/"))
+            },
+            BoardParseError::WrongSize {found, expected} =>
+                write!(f, "board is {}x{}, expected {}x{}", found, found, expected, expected),
+            BoardParseError::BadPiece {index, found} =>
+                write!(f, "square {}: {}", index, PieceParseError(*found)),
+            BoardParseError::BadBorder {index, found} =>
+                write!(f, "square {} is on the border and must be '?', found {:?}", index, char::from(found)),
+            BoardParseError::BadInterior {index} =>
+                write!(f, "square {} is on the board and must be '@', 'o' or '.', found '?'", index),
+        }
+    }
+}
+
+impl Error for BoardParseError {}
+
 impl From<&Piece> for Player {
     fn from (pc: &Piece) -> Player {
         match pc {
@@ -44,17 +101,88 @@ impl From<Piece> for char {
     }
 }
 
-impl From<char> for Piece {
-    fn from (c: char) -> Piece {
+impl TryFrom<char> for Piece {
+    type Error = PieceParseError;
+
+    fn try_from (c: char) -> Result<Piece, PieceParseError> {
         match c {
-            '@' => Piece::BLACK,
-            'o' => Piece::WHITE,
-            '.' => Piece::EMPTY,
-            '?' => Piece::OUTER,
-            // Fail "gracefully," i.e. undetectably and this will most definitely
-            // come to haunt me later oops
-            _ => Piece::OUTER,
+            '@' => Ok(Piece::BLACK),
+            'o' => Ok(Piece::WHITE),
+            '.' => Ok(Piece::EMPTY),
+            '?' => Ok(Piece::OUTER),
+            _ => Err(PieceParseError(c)),
+        }
+    }
+}
+
+// The board whose mailbox (or playing area, if `bordered` is false) has
+// `len` squares
+fn geometry_for(len: usize, bordered: bool) -> Option<&'static Geometry> {
+    bitboard::SIZES
+        .iter()
+        .find(|size| {
+            let side = if bordered { *size + 2 } else { **size };
+            side * side == len
+        })
+        .and_then(|size| Geometry::for_size(*size))
+}
+
+impl TryFrom<&[Piece]> for BoardStruct {
+    type Error = BoardParseError;
+
+    // Works out the size from the length. Unlike from_mailbox, insists the
+    // border is all OUTER and nothing inside is
+    fn try_from (board: &[Piece]) -> Result<BoardStruct, BoardParseError> {
+        let geometry = geometry_for(board.len(), true)
+            .ok_or(BoardParseError::WrongLength(board.len()))?;
+        for (index, piece) in board.iter().enumerate() {
+            let inside = geometry.square_to_bit(index).is_some();
+            match (inside, piece) {
+                (true, Piece::OUTER) => return Err(BoardParseError::BadInterior {index}),
+                (false, Piece::OUTER) | (true, _) => (),
+                (false, found) => return Err(BoardParseError::BadBorder {index, found: *found}),
+            }
+        }
+        Ok(BoardStruct::from_mailbox(geometry, board))
+    }
+}
+
+impl FromStr for BoardStruct {
+    type Err = BoardParseError;
+
+    // Takes either the full mailbox or just the squares on the board, row by
+    // row. Some lengths could be either (100 is the standard mailbox or a
+    // bare 10x10 board), but a mailbox always starts with a '?' and the
+    // playing area never has one
+    fn from_str (s: &str) -> Result<BoardStruct, BoardParseError> {
+        let len = s.chars().count();
+        let bordered = s.starts_with('?');
+        let geometry = geometry_for(len, bordered)
+            .ok_or(BoardParseError::WrongLength(len))?;
+        let positions : Vec<usize> = if bordered {
+            (0..len).collect()
+        } else {
+            geometry.legal_spaces().collect()
+        };
+
+        let width = geometry.width();
+        let mut board : Board = vec![Piece::OUTER; width * width];
+        for (index, c) in positions.into_iter().zip(s.chars()) {
+            board[index] = Piece::try_from(c)
+                .map_err(|_| BoardParseError::BadPiece {index, found: c})?;
+        }
+        BoardStruct::try_from(&board[..])
+    }
+}
+
+impl BoardStruct {
+    // Like parse, but only for boards `size` squares across
+    pub fn parse_sized (s: &str, size: usize) -> Result<BoardStruct, BoardParseError> {
+        let board : BoardStruct = s.parse()?;
+        if board.size() != size {
+            return Err(BoardParseError::WrongSize {found: board.size(), expected: size});
         }
+        Ok(board)
     }
 }
 
@@ -0,0 +1,150 @@
+// Exact solving of positions near the end of the game
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::othello::*;
+use crate::othello::bitboard::{Bits, Geometry};
+use crate::othello::moves::Loc;
+
+// Past this many empty squares, solving takes far too long
+pub const MAX_EMPTIES : u32 = 20;
+
+// Below this many empties, sorting moves costs more than it saves
+const ORDERING_EMPTIES : u32 = 6;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Solution {
+    // Final disc differential for the player to move under perfect play.
+    // Empty squares at the end go to the winner, as in tournament scoring.
+    // In anti-reversi this is flipped, so higher is still better
+    pub score: i32,
+    // None if the player has to pass, or the game is already over
+    pub best_move: Option<Loc>,
+}
+
+// Returns None if `player` isn't a real player or there are too many
+// empties. With `anti` set, fewer discs is better
+pub fn solve(board: &BoardStruct, player: &Player, anti: bool) -> Option<Solution> {
+    solve_until(board, player, anti, &AtomicBool::new(false))
+}
+
+// Like solve, but gives up and returns None once `stop` is set
+pub fn solve_until(board: &BoardStruct, player: &Player, anti: bool, stop: &AtomicBool) -> Option<Solution> {
+    if player == &Player::Unknown {
+        return None;
+    }
+    let search = Search {g: board.geometry(), anti, stop};
+    let (own, opp) = board.bitboards(player);
+    let solution = if search.g.fits_u64() {
+        search.solve(u64::narrow(own), u64::narrow(opp))
+    } else {
+        search.solve(own, opp)
+    };
+    // Scores from a search that was cut short mean nothing
+    solution.filter(|_| !stop.load(Ordering::Relaxed))
+}
+
+// What stays the same through a whole search
+struct Search<'a> {
+    g: &'static Geometry,
+    anti: bool,
+    // Checked at every node
+    stop: &'a AtomicBool,
+}
+
+impl Search<'_> {
+    fn solve<B: Bits>(&self, own: B, opp: B) -> Option<Solution> {
+        if self.empties(own, opp) > MAX_EMPTIES {
+            return None;
+        }
+
+        let bound = self.g.area() as i32;
+        let moves = self.g.legal_moves(own, opp);
+        if moves == B::ZERO {
+            let score = if self.g.legal_moves(opp, own) == B::ZERO {
+                self.final_score(own, opp)
+            } else {
+                -self.negamax(opp, own, -bound, bound, true)
+            };
+            return Some(Solution {score, best_move: None});
+        }
+
+        let mut best = None;
+        let mut alpha = -bound - 1;
+        for bit in self.order_moves(own, opp, moves) {
+            let (new_own, new_opp) = self.play(own, opp, bit);
+            let score = -self.negamax(new_opp, new_own, -bound, -alpha, false);
+            if score > alpha {
+                alpha = score;
+                best = Some(self.g.bit_to_square(bit));
+            }
+        }
+        Some(Solution {score: alpha, best_move: best})
+    }
+
+    fn empties<B: Bits>(&self, own: B, opp: B) -> u32 {
+        self.g.area() - (own | opp).count_ones()
+    }
+
+    fn final_score<B: Bits>(&self, own: B, opp: B) -> i32 {
+        let diff = own.count_ones() as i32 - opp.count_ones() as i32;
+        let left = self.empties(own, opp) as i32;
+        let score = if diff > 0 {
+            diff + left
+        } else if diff < 0 {
+            diff - left
+        } else {
+            0
+        };
+        // Whoever wins anti-reversi by n has n fewer discs, plus the empties
+        if self.anti { -score } else { score }
+    }
+
+    fn play<B: Bits>(&self, own: B, opp: B, bit: u32) -> (B, B) {
+        let placed = B::bit(bit);
+        let flipped = self.g.flips(own, opp, placed);
+        (own | placed | flipped, opp & !flipped)
+    }
+
+    // Fastest-first: moves that leave the opponent the fewest replies go first
+    fn order_moves<B: Bits>(&self, own: B, opp: B, mut moves: B) -> Vec<u32> {
+        let mut ordered = Vec::with_capacity(moves.count_ones() as usize);
+        while moves != B::ZERO {
+            let bit = moves.trailing_zeros();
+            moves &= moves - B::ONE;
+            ordered.push(bit);
+        }
+        if self.empties(own, opp) > ORDERING_EMPTIES {
+            ordered.sort_by_key(|bit| {
+                let (new_own, new_opp) = self.play(own, opp, *bit);
+                self.g.legal_moves(new_opp, new_own).count_ones()
+            });
+        }
+        ordered
+    }
+
+    // Negamax from the point of view of `own`, who is to move
+    fn negamax<B: Bits>(&self, own: B, opp: B, mut alpha: i32, beta: i32, passed: bool) -> i32 {
+        if self.stop.load(Ordering::Relaxed) {
+            return 0;
+        }
+        let moves = self.g.legal_moves(own, opp);
+        if moves == B::ZERO {
+            if passed {
+                return self.final_score(own, opp);
+            }
+            return -self.negamax(opp, own, -beta, -alpha, true);
+        }
+
+        for bit in self.order_moves(own, opp, moves) {
+            let (new_own, new_opp) = self.play(own, opp, bit);
+            let score = -self.negamax(new_opp, new_own, -beta, -alpha, false);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+}
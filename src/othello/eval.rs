@@ -0,0 +1,167 @@
+// Measurements of a position that say more than the disc count does
+use serde::{Serialize, Deserialize};
+
+use crate::othello::*;
+use crate::othello::bitboard::{Bitboard, Bits, Geometry};
+use crate::othello::record::GameRecord;
+
+fn corners<B: Bits> (g: &Geometry) -> B {
+    let last = g.size - 1;
+    [0, last, last * g.size, last * g.size + last]
+        .iter()
+        .fold(B::ZERO, |acc, bit| acc | B::ONE << *bit)
+}
+
+// The squares diagonally next to the corners, like b2 and g7
+fn x_squares<B: Bits> (g: &Geometry) -> B {
+    let near = g.size - 2;
+    [g.size + 1, g.size + near, near * g.size + 1, near * g.size + near]
+        .iter()
+        .fold(B::ZERO, |acc, bit| acc | B::ONE << *bit)
+}
+
+// Measurements for one player
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub discs: u32,
+    // Legal moves right now
+    pub mobility: u32,
+    // Empty squares next to the opponent's discs, i.e. places moves could
+    // show up later
+    pub potential_mobility: u32,
+    // Discs next to an empty square, which tend to get flipped
+    pub frontier: u32,
+    // Discs that can never be flipped again. This errs on the side of
+    // calling discs unstable
+    pub stable: u32,
+    pub corners: u32,
+    pub x_squares: u32,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub black: Metrics,
+    pub white: Metrics,
+    pub empties: u32,
+    // Empty regions with an odd number of squares. Whoever gets the last
+    // move in more of these usually does well in the endgame
+    pub odd_regions: u32,
+}
+
+// Every square next to one in `b`
+fn neighbours<B: Bits> (g: &Geometry, b: B) -> B {
+    Direction::iter().fold(B::ZERO, |acc, d| acc | g.shift(d, b))
+}
+
+// Squares whose whole line in direction `d` (both ways) is filled
+fn full_lines<B: Bits> (g: &Geometry, filled: B, d: &Direction, back: &Direction) -> B {
+    // Spread the empty squares along the line in both directions
+    let mut open = g.all_bits::<B>() & !filled;
+    for _ in 0..g.size - 1 {
+        open |= g.shift(d, open) | g.shift(back, open);
+    }
+    g.all_bits::<B>() & !open
+}
+
+// Grows the set of stable discs from nothing until it stops changing. A
+// disc is stable if, along every line through it, either the line is full
+// or one of its neighbours on that line is the edge or another stable disc
+pub fn stable_discs<B: Bits> (g: &Geometry, own: B, opp: B) -> B {
+    let filled = own | opp;
+    let axes = [
+        (Direction::Left, Direction::Right),
+        (Direction::Up, Direction::Down),
+        (Direction::UpLeft, Direction::DownRight),
+        (Direction::UpRight, Direction::DownLeft),
+    ];
+    let full : Vec<B> = axes
+        .iter()
+        .map(|(d, back)| full_lines(g, filled, d, back))
+        .collect();
+
+    let mut stable = B::ZERO;
+    loop {
+        let mut next = own;
+        for (i, (d, back)) in axes.iter().enumerate() {
+            // Shifting stable discs (or the whole board, for the edge) one
+            // step lands them on their neighbours
+            let anchored = g.shift(d, stable) | g.shift(back, stable)
+                | !g.shift(d, g.all_bits::<B>()) | !g.shift(back, g.all_bits::<B>());
+            next &= full[i] | anchored;
+        }
+        if next == stable {
+            return stable;
+        }
+        stable = next;
+    }
+}
+
+pub fn metrics<B: Bits> (g: &Geometry, own: B, opp: B) -> Metrics {
+    let empty = g.all_bits::<B>() & !(own | opp);
+    Metrics {
+        discs: own.count_ones(),
+        mobility: g.legal_moves(own, opp).count_ones(),
+        potential_mobility: (neighbours(g, opp) & empty).count_ones(),
+        frontier: (neighbours(g, empty) & own).count_ones(),
+        stable: stable_discs(g, own, opp).count_ones(),
+        corners: (own & corners::<B>(g)).count_ones(),
+        x_squares: (own & x_squares::<B>(g)).count_ones(),
+    }
+}
+
+fn odd_regions (g: &Geometry, empty: Bitboard) -> u32 {
+    let mut left = empty;
+    let mut odd = 0;
+    while left != 0 {
+        // Flood fill from the lowest empty square
+        let mut region = left & left.wrapping_neg();
+        loop {
+            let grown = (region | neighbours(g, region)) & empty;
+            if grown == region {
+                break;
+            }
+            region = grown;
+        }
+        if region.count_ones() % 2 == 1 {
+            odd += 1;
+        }
+        left &= !region;
+    }
+    odd
+}
+
+pub fn evaluate (board: &BoardStruct) -> Evaluation {
+    let g = board.geometry();
+    let (black, white) = board.bitboards(&Player::Black);
+    let empty = g.all & !(black | white);
+    Evaluation {
+        black: metrics(g, black, white),
+        white: metrics(g, white, black),
+        empties: empty.count_ones(),
+        odd_regions: odd_regions(g, empty),
+    }
+}
+
+// Every position of a game, from the start to where it is now
+pub fn report (record: &GameRecord) -> Vec<Evaluation> {
+    record.positions()
+        .map(|(board, _)| evaluate(&board))
+        .collect()
+}
+
+// A single number for how good the position is for `own`, for searching
+pub fn heuristic<B: Bits> (g: &Geometry, own: B, opp: B) -> i32 {
+    let (mine, theirs) = (metrics(g, own, opp), metrics(g, opp, own));
+    let diff = |a: u32, b: u32| a as i32 - b as i32;
+
+    // X-squares are only a liability while their corner is still open
+    let empty_corners = corners::<B>(g) & !(own | opp);
+    let open_x = |b: B| (b & x_squares::<B>(g) & neighbours(g, empty_corners)).count_ones();
+
+    30 * diff(mine.corners, theirs.corners)
+        + 10 * diff(mine.stable, theirs.stable)
+        - 12 * diff(open_x(own), open_x(opp))
+        + 6 * diff(mine.mobility, theirs.mobility)
+        + 2 * diff(mine.potential_mobility, theirs.potential_mobility)
+        - 2 * diff(mine.frontier, theirs.frontier)
+}
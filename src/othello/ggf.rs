@@ -0,0 +1,243 @@
+// Reading and writing games in GGF, the Generic Game Format used by
+// NBoard, WZebra, and the GGS archives. A game looks like
+// (;GM[Othello]PB[black]PW[white]RE[+12]TY[8]BO[8 <64 squares> *]B[f5]W[d6]...;)
+use crate::othello::*;
+use crate::othello::bitboard::Geometry;
+use crate::othello::moves::{score, Loc};
+use crate::othello::notation::{from_algebraic, to_algebraic};
+use crate::othello::record::GameRecord;
+
+#[derive(Clone, Debug)]
+pub struct GgfGame {
+    pub black: String,
+    pub white: String,
+    // Final disc differential for black, if known
+    pub result: Option<i32>,
+    // Any other properties, like PC (place) or DT (date), in file order
+    pub tags: Vec<(String, String)>,
+    pub record: GameRecord,
+}
+
+impl GgfGame {
+    // Fills in the result from the final position if the game is over
+    pub fn new(black: &str, white: &str, record: GameRecord) -> Self {
+        let result = if record.is_over() {
+            Some(score(&Player::Black, record.board()))
+        } else {
+            None
+        };
+        GgfGame {
+            black: black.to_string(),
+            white: white.to_string(),
+            result,
+            tags: Vec::new(),
+            record,
+        }
+    }
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::BLACK => '*',
+        Piece::WHITE => 'O',
+        _ => '-',
+    }
+}
+
+fn player_char(player: &Player) -> char {
+    match player {
+        Player::White => 'O',
+        _ => '*',
+    }
+}
+
+fn move_tag(player: &Player) -> &'static str {
+    match player {
+        Player::White => "W",
+        _ => "B",
+    }
+}
+
+pub fn to_ggf(game: &GgfGame) -> String {
+    let mut out = String::from("(;GM[Othello]");
+    for (tag, value) in game.tags.iter() {
+        out.push_str(&format!("{}[{}]", tag, value));
+    }
+    out.push_str(&format!("PB[{}]PW[{}]", game.black, game.white));
+    if let Some(result) = game.result {
+        out.push_str(&format!("RE[{:+}]", result));
+    }
+
+    let (start, start_player) = game.record.start();
+    let g = start.geometry();
+    let squares : String = start.to_mailbox()
+        .iter()
+        .filter(|pc| **pc != Piece::OUTER)
+        .map(|pc| piece_char(*pc))
+        .collect();
+    out.push_str(&format!("TY[{}]BO[{} {} {}]", g.size, g.size, squares, player_char(start_player)));
+
+    for record in game.record.moves() {
+        let square = match record.square {
+            Some(sq) => to_algebraic(g, sq).unwrap_or_default(),
+            None => "pa".to_string(),
+        };
+        out.push_str(&format!("{}[{}]", move_tag(&record.player), square));
+    }
+    out.push_str(";)");
+    out
+}
+
+// Reads every game in a file
+pub fn parse_ggf_all(text: &str) -> Result<Vec<GgfGame>, String> {
+    let mut games = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("(;") {
+        let end = match rest[start..].find(";)") {
+            Some(end) => start + end + 2,
+            None => return Err("game is missing its closing \";)\"".to_string()),
+        };
+        games.push(parse_ggf(&rest[start..end])?);
+        rest = &rest[end..];
+    }
+    Ok(games)
+}
+
+pub fn parse_ggf(text: &str) -> Result<GgfGame, String> {
+    let text = text.trim();
+    // "(;)" starts and ends right, but there's nothing between the two
+    if text.len() < 4 || !text.starts_with("(;") || !text.ends_with(";)") {
+        return Err("a GGF game must be wrapped in \"(;\" and \";)\"".to_string());
+    }
+
+    let mut black = String::new();
+    let mut white = String::new();
+    let mut result = None;
+    let mut tags = Vec::new();
+    let mut record : Option<GameRecord> = None;
+    let mut geometry : &'static Geometry = &bitboard::STANDARD;
+
+    for (tag, value) in properties(&text[2..text.len() - 2])? {
+        match tag.as_str() {
+            "GM" if value.to_lowercase() != "othello" => {
+                return Err(format!("not an Othello game: {}", value));
+            },
+            "GM" => (),
+            "PB" => black = value,
+            "PW" => white = value,
+            "RE" => result = parse_result(&value),
+            // Just the board size, variants like random starts aren't supported
+            "TY" => {
+                geometry = value.trim().parse::<usize>().ok()
+                    .and_then(Geometry::for_size)
+                    .ok_or_else(|| format!("unsupported game type {}", value))?;
+            },
+            "BO" => {
+                // It would throw away the moves before it
+                if record.as_ref().is_some_and(|game| !game.moves().is_empty()) {
+                    return Err("the board has to come before the moves".to_string());
+                }
+                let game = parse_board(&value)?;
+                if game.board().geometry() != geometry {
+                    return Err(format!("board \"{}\" doesn't match the game type", value));
+                }
+                record = Some(game);
+            },
+            "B" | "W" => {
+                let game = record.get_or_insert_with(|| initial_record(geometry));
+                play_ggf_move(game, &tag, &value)?;
+            },
+            _ => tags.push((tag, value)),
+        }
+    }
+
+    Ok(GgfGame {
+        black,
+        white,
+        result,
+        tags,
+        record: record.unwrap_or_else(|| initial_record(geometry)),
+    })
+}
+
+// Games with no BO start from the usual position on their TY's board
+fn initial_record(geometry: &'static Geometry) -> GameRecord {
+    GameRecord::from_position(BoardStruct::initial(geometry), Player::Black)
+}
+
+// Splits "AB[x]CD[y]" into its tags and values
+fn properties(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut props = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let open = rest.find('[').ok_or_else(|| format!("expected a property at \"{}\"", rest))?;
+        let close = rest[open..].find(']').ok_or_else(|| "unclosed property value".to_string())? + open;
+        let tag = rest[..open].trim().to_string();
+        if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("bad property name \"{}\"", tag));
+        }
+        props.push((tag, rest[open + 1..close].to_string()));
+        rest = rest[close + 1..].trim_start();
+    }
+    Ok(props)
+}
+
+// Results look like "+12", "-4.000", or "+64:r" for a resignation
+fn parse_result(value: &str) -> Option<i32> {
+    let number = value.split(':').next()?.trim();
+    number.parse::<f32>().ok().map(|r| r.round() as i32)
+}
+
+fn parse_board(value: &str) -> Result<GameRecord, String> {
+    let mut tokens = value.split_whitespace();
+    let geometry = tokens.next()
+        .and_then(|size| size.parse::<usize>().ok())
+        .and_then(Geometry::for_size)
+        .ok_or_else(|| format!("unsupported board size in \"{}\"", value))?;
+
+    let cells : Vec<char> = tokens.flat_map(|token| token.chars()).collect();
+    let area = geometry.area() as usize;
+    if cells.len() != area + 1 {
+        return Err(format!("board \"{}\" should have {} squares and a side to move", value, area));
+    }
+    let mut board = BoardStruct::initial(geometry);
+    for (index, cell) in cells[..area].iter().enumerate() {
+        let sq = notation::from_index(geometry, index).unwrap();
+        let piece = match cell {
+            '*' | 'x' | 'X' | '#' => Piece::BLACK,
+            'O' | 'o' => Piece::WHITE,
+            '-' | '.' => Piece::EMPTY,
+            other => return Err(format!("unknown square '{}' in board", other)),
+        };
+        board.set(sq, piece);
+    }
+    let tomove = match cells[area] {
+        '*' | 'x' | 'X' | '#' => Player::Black,
+        'O' | 'o' => Player::White,
+        other => return Err(format!("unknown side to move '{}'", other)),
+    };
+    Ok(GameRecord::from_position(board, tomove))
+}
+
+// Moves look like "f5", "F5/1.23/4.5" with an evaluation and time, or "pa"
+fn play_ggf_move(game: &mut GameRecord, tag: &str, value: &str) -> Result<(), String> {
+    let player = if tag == "B" { Player::Black } else { Player::White };
+    let square = value.split('/').next().unwrap_or("").trim().to_lowercase();
+
+    // Some writers leave out forced passes
+    if game.tomove() != player && game.must_pass() {
+        game.pass().map_err(|why| why.to_string())?;
+    }
+    if game.tomove() != player {
+        return Err(format!("{} moved out of turn with \"{}\"", tag, value));
+    }
+
+    if square == "pa" || square == "pass" {
+        game.pass().map_err(|_| format!("{} passed with legal moves available", tag))?;
+        return Ok(());
+    }
+    let sq : Loc = from_algebraic(game.board().geometry(), &square).ok_or_else(|| format!("\"{}\" is not a square", value))?;
+    game.play(sq).map_err(|_| format!("{} played illegal move {}", tag, square))?;
+    Ok(())
+}
+
@@ -0,0 +1,69 @@
+// Zobrist hashing. Every (colour, square) pair gets a fixed random key, and
+// a position's hash is the xor of the keys of its discs. Boards keep their
+// hash up to date as moves are made, see BoardStruct::toggle_move
+use crate::othello::*;
+use crate::othello::bitboard::Bitboard;
+
+// Same numbers every run, so hashes can be saved and compared across games
+const fn splitmix64 (state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+// One key per square of the largest board, for each colour
+const SQUARES : usize = 100;
+
+const fn make_keys (seed: u64) -> [u64; 2 * SQUARES + 1] {
+    let mut keys = [0; 2 * SQUARES + 1];
+    let mut state = seed;
+    let mut i = 0;
+    while i < 2 * SQUARES + 1 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+// Keys for black discs, then white discs, then one for black to move
+const KEYS : [u64; 2 * SQUARES + 1] = make_keys(0x07be_1105);
+const BLACK_TO_MOVE : u64 = KEYS[2 * SQUARES];
+
+fn keys_for (player: &Player) -> &'static [u64] {
+    match player {
+        Player::White => &KEYS[SQUARES..2 * SQUARES],
+        _ => &KEYS[..SQUARES],
+    }
+}
+
+pub fn disc_keys (player: &Player, mut discs: Bitboard) -> u64 {
+    let keys = keys_for(player);
+    let mut hash = 0;
+    while discs != 0 {
+        hash ^= keys[discs.trailing_zeros() as usize];
+        discs &= discs - 1;
+    }
+    hash
+}
+
+// Hash of just the discs, from scratch
+pub fn hash_discs (black: Bitboard, white: Bitboard) -> u64 {
+    disc_keys(&Player::Black, black) ^ disc_keys(&Player::White, white)
+}
+
+// Hash of the discs and the side to move
+pub fn zobrist (board: &BoardStruct, tomove: &Player) -> u64 {
+    match tomove {
+        Player::Black => board.hash() ^ BLACK_TO_MOVE,
+        _ => board.hash(),
+    }
+}
+
+// Hash that is the same for all eight symmetric versions of a position
+pub fn canonical_zobrist (board: &BoardStruct, tomove: &Player) -> u64 {
+    zobrist(&symmetry::canonical(board).0, tomove)
+}
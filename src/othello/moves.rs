@@ -1,136 +1,170 @@
 use crate::othello::*;
+use crate::othello::bitboard::{Bitboard, Bits};
 
-type Loc = usize;
+pub type Loc = usize;
 type Bd = BoardStruct;
 
-#[derive(Debug)]
+// One move of a game, with enough detail to animate, replay, or undo it
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MoveRecord {
+    pub player: Player,
+    // None for a pass
+    pub square: Option<Loc>,
+    pub flipped: Vec<Loc>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum IllegalMoveReason {
+    #[serde(rename = "occupied")]
+    Occupied,
+    // The square is empty but no line of opponent discs ends at it
+    #[serde(rename = "no_bracket")]
+    NoBracket,
+    #[serde(rename = "off_board")]
+    OffBoard,
+    // Passing while there are moves to make
+    #[serde(rename = "must_move")]
+    MustMove,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct IllegalMoveError {
-    player: Player,
-    square: Loc,
-    board: Bd,
+    pub player: Player,
+    // None for a pass
+    pub square: Option<Loc>,
+    pub board: Bd,
+    pub reason: IllegalMoveReason,
 }
 
-// Taken from https://stackoverflow.com/a/54035801
-fn add (u: usize, i: i32) -> usize {
-    if i.is_negative() {
-        u - i.wrapping_abs() as u32 as usize
-    } else {
-        u + i as usize
+impl std::fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IllegalMoveReason::Occupied => "the square is already taken",
+            IllegalMoveReason::NoBracket => "it doesn't flip any discs",
+            IllegalMoveReason::OffBoard => "the square is not on the board",
+            IllegalMoveReason::MustMove => "there are legal moves to make",
+        })
     }
 }
 
-fn find_bracket(square: &Loc, player: &Player, board: &Bd, direction: &Direction) -> Option<Loc> {
-    let dir = direction.value();
-    let board = board.board;
-    
-    let mut bracket : Loc = add(square.clone(), dir);
-    
-    if board[bracket] == player.into() {
-        return None;
+impl std::fmt::Display for IllegalMoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.square {
+            Some(sq) => write!(f, "{:?} can't play {}: {}", self.player, sq, self.reason),
+            None => write!(f, "{:?} can't pass: {}", self.player, self.reason),
+        }
     }
+}
 
-    let opp : Piece = player.opponent().into();
-    while board[bracket] == opp {
-        bracket = add(bracket, dir);
-    }
-    
-    if board[bracket] == player.into() {
-        return Some(bracket);
+impl std::error::Error for IllegalMoveError {}
+
+// The squares `player` can move to. Boards that fit in a u64 are worked on
+// in one, see bitboard::Bits
+fn move_bits(player: &Player, board: &Bd) -> Bitboard {
+    let g = board.geometry();
+    let (own, opp) = board.bitboards(player);
+    if g.fits_u64() {
+        g.legal_moves(u64::narrow(own), u64::narrow(opp)).widen()
     } else {
-        return None;
+        g.legal_moves(own, opp)
     }
 }
 
 pub fn is_legal(square: &Loc, player: &Player, board: &Bd) -> bool {
-    if board.board[square.clone()] != Piece::EMPTY {
-        return false;
-    }
+    // Squares can come straight from clients, so they might be anything
+    move_bits(player, board) & board.geometry().square_mask(*square) != 0
+}
 
-    Direction::iter().any(
-        |d: &Direction| find_bracket(square, player, board, d).is_some()
-    )
-}
-
-fn make_flips(square: &Loc, player: &Player, board: &mut Bd, direction: &Direction) -> () {
-    let bracket = find_bracket(square, player, board, direction);
-    let mut bd = board.board;
-    let dir = direction.value();
-
-    match bracket {
-        None => (),
-        Some(endpoint) => {
-            let mut flipping = add(square.clone(), dir);
-            while flipping != endpoint {
-                bd[flipping] = player.into();
-                flipping = add(flipping, dir);
-            }
-            board.board = bd;
+// The discs a move would flip, or why it can't be played
+fn find_flips(square: &Loc, player: &Player, board: &Bd) -> Result<Bitboard, IllegalMoveError> {
+    let g = board.geometry();
+    let (own, opp) = board.bitboards(player);
+    let placed = g.square_mask(*square);
+    let reason = if placed == 0 {
+        IllegalMoveReason::OffBoard
+    } else if (own | opp) & placed != 0 {
+        IllegalMoveReason::Occupied
+    } else {
+        let flipped = if g.fits_u64() {
+            g.flips(u64::narrow(own), u64::narrow(opp), u64::narrow(placed)).widen()
+        } else {
+            g.flips(own, opp, placed)
+        };
+        match flipped {
+            0 => IllegalMoveReason::NoBracket,
+            flipped => return Ok(flipped),
         }
-    }
+    };
+
+    Err(IllegalMoveError {
+        player: *player,
+        square: Some(*square),
+        board: *board,
+        reason,
+    })
 }
 
-pub fn make_move(square: &Loc, player: &Player, board: &mut Bd) -> Result<(), IllegalMoveError> {
-    if !is_legal(square, player, board) {
+// Like is_legal, but says what is wrong with the move
+pub fn check_move(square: &Loc, player: &Player, board: &Bd) -> Result<(), IllegalMoveError> {
+    find_flips(square, player, board).map(|_| ())
+}
+
+// Returns the squares that were flipped by the move
+pub fn make_move(square: &Loc, player: &Player, board: &mut Bd) -> Result<Vec<Loc>, IllegalMoveError> {
+    let g = board.geometry();
+    let flipped = find_flips(square, player, board)?;
+    board.toggle_move(player, g.square_mask(*square), flipped);
+    Ok(g.squares(flipped).collect())
+}
+
+// Takes back a move previously made on this board
+pub fn unmake_move(record: &MoveRecord, board: &mut Bd) {
+    let g = board.geometry();
+    let placed = match record.square {
+        Some(sq) => g.square_mask(sq),
+        None => return,
+    };
+    let flipped = record.flipped
+        .iter()
+        .fold(0, |acc, sq| acc | g.square_mask(*sq));
+    board.toggle_move(&record.player, placed, flipped);
+}
+
+// Passing is only allowed when there is nothing else to do
+pub fn make_pass(player: &Player, board: &Bd) -> Result<MoveRecord, IllegalMoveError> {
+    if any_legal_moves(player, board) || player == &Player::Unknown {
         return Err(IllegalMoveError {
-            square: square.clone(),
-            player: player.clone(),
-            board: board.clone()
+            player: *player,
+            square: None,
+            board: *board,
+            reason: IllegalMoveReason::MustMove,
         });
     }
-    board.board[square.clone()] = player.into();
-    for d in Direction::iter() {
-        make_flips(square, player, board, d);
-    }
-    Ok(())
+    Ok(MoveRecord {player: *player, square: None, flipped: Vec::new()})
 }
 
 pub fn legal_moves (player: &Player, board: &Bd) -> Vec<Loc> {
-    LEGAL_SPACES
-        .iter()
-        .filter_map(|sq: &Loc| {
-            if is_legal(sq, player, board) {
-                Some(*sq)
-            } else {
-                None
-            }
-        })
-        .collect()
+    board.geometry().squares(move_bits(player, board)).collect()
 }
 
 pub fn any_legal_moves (player: &Player, board: &Bd) -> bool {
-    LEGAL_SPACES
-        .iter()
-        .any(|sq: &Loc| {
-            is_legal(sq, player, board)
-        })
+    move_bits(player, board) != 0
 }
 
 pub fn next_player (board: &Bd, prev_player: &Player) -> Option<Player> {
     let opp = prev_player.opponent();
     if any_legal_moves(&opp, board) {
-        return Some(opp);
+        Some(opp)
     } else if any_legal_moves(prev_player, board) {
-        return Some(prev_player.clone());
+        Some(*prev_player)
     } else {
-        return None
+        None
     }
 }
 
 pub fn score (player: &Player, board: &Bd) -> i32 {
-    let mine : Piece = player.into();
-    let theirs : Piece = player.opponent().into();
-    board.board
-        .iter()
-        .map(|sq: &Piece| {
-            if sq == &mine {
-                1
-            } else if sq == &theirs {
-                -1
-            } else {
-                0
-            }
-        })
-        .fold(0, |acc, x| acc + x)
+    let (own, opp) = board.bitboards(player);
+    own.count_ones() as i32 - opp.count_ones() as i32
 }
 
 pub fn winner (board: &Bd) -> Player {
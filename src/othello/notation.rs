@@ -0,0 +1,75 @@
+// Conversions between the ways squares get written down:
+// * TJHSST numbering, the mailbox index (11..88 on the standard board) used
+//   everywhere else
+// * a 0-based index, going across each row from the top left (0..63 on the
+//   standard board)
+// * algebraic coordinates, columns a, b, c... and rows 1, 2, 3... (a1 is the
+//   top left)
+use crate::othello::bitboard::Geometry;
+use crate::othello::moves::Loc;
+
+pub fn to_index (geometry: &Geometry, square: Loc) -> Option<usize> {
+    geometry.square_to_bit(square).map(|bit| bit as usize)
+}
+
+pub fn from_index (geometry: &Geometry, index: usize) -> Option<Loc> {
+    if index < geometry.area() as usize {
+        Some(geometry.bit_to_square(index as u32))
+    } else {
+        None
+    }
+}
+
+pub fn to_algebraic (geometry: &Geometry, square: Loc) -> Option<String> {
+    let index = to_index(geometry, square)?;
+    let col = (b'a' + (index % geometry.size) as u8) as char;
+    let row = index / geometry.size + 1;
+    Some(format!("{}{}", col, row))
+}
+
+// Either case is accepted
+pub fn from_algebraic (geometry: &Geometry, name: &str) -> Option<Loc> {
+    let mut chars = name.chars();
+    let col = chars.next()?.to_ascii_lowercase();
+    let row = chars.as_str();
+    if !col.is_ascii_lowercase() || row.is_empty() || !row.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (col, row) = (col as usize - 'a' as usize, row.parse::<usize>().ok()?);
+    if col >= geometry.size || row < 1 || row > geometry.size {
+        return None;
+    }
+    from_index(geometry, (row - 1) * geometry.size + col)
+}
+
+// Writes moves one after another, like "f5d6c3"
+pub fn format_moves (geometry: &Geometry, squares: &[Loc]) -> String {
+    squares
+        .iter()
+        .filter_map(|sq| to_algebraic(geometry, *sq))
+        .collect()
+}
+
+// Reads move strings like "f5d6c3". Every move starts with its column
+// letter, which is how moves with two digit rows are told apart. Whitespace
+// between moves is ignored
+pub fn parse_moves (geometry: &Geometry, moves: &str) -> Result<Vec<Loc>, String> {
+    let chars : Vec<char> = moves
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    let mut squares = Vec::new();
+    let mut rest = &chars[..];
+    while let Some(col) = rest.first() {
+        let digits = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return Err(format!("move string \"{}\" has a dangling character '{}'", moves, col));
+        }
+        let name : String = rest[..1 + digits].iter().collect();
+        squares.push(from_algebraic(geometry, &name).ok_or_else(|| format!("\"{}\" is not a square", name))?);
+        rest = &rest[1 + digits..];
+    }
+    Ok(squares)
+}
@@ -0,0 +1,134 @@
+// Named openings on the standard board, recognised in any orientation
+use std::sync::OnceLock;
+
+use crate::othello::*;
+use crate::othello::moves::Loc;
+use crate::othello::notation::parse_moves;
+use crate::othello::record::GameRecord;
+use crate::othello::symmetry::Symmetry;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Opening {
+    pub name: &'static str,
+    // Starting with f5, like every published line
+    pub moves: &'static str,
+}
+
+// The named lines from the standard list, each in its f5 orientation. When
+// lines overlap the longest match wins, so more specific lines can be added
+// freely
+pub const OPENINGS : &[Opening] = &[
+    Opening {name: "Diagonal Opening", moves: "f5f6"},
+    Opening {name: "X-square Opening", moves: "f5f6e6f4g7"},
+    Opening {name: "Snake, Peasant, or Battle", moves: "f5f6e6f4g6"},
+    Opening {name: "Lysons", moves: "f5f6e6f4g6c6"},
+    Opening {name: "Pyramid, Checkerboarding Peasant", moves: "f5f6e6f4g6c5g4g5f3e3c4"},
+    Opening {name: "Heath, Tobidashi", moves: "f5f6e6f4g5"},
+    Opening {name: "Mimura Variation II", moves: "f5f6e6f4g5e7f7c5e3f3c4d3c2"},
+    Opening {name: "Heath-Bat", moves: "f5f6e6f4g5e7e3"},
+    Opening {name: "Iwasaki Variation", moves: "f5f6e6f4g5e7d7"},
+    Opening {name: "Heath-Chimney", moves: "f5f6e6f4g5d6"},
+    Opening {name: "Raccoon Dog", moves: "f5f6e6f4g4"},
+    Opening {name: "Rocket", moves: "f5f6e6f4g3"},
+    Opening {name: "Hamilton", moves: "f5f6e6f4g3f3g4"},
+    Opening {name: "Lollipop", moves: "f5f6e6f4g3d6"},
+    Opening {name: "Cow", moves: "f5f6e6f4e3"},
+    Opening {name: "Chimney", moves: "f5f6e6f4e3d6"},
+    Opening {name: "Cow Bat, Bat, or Cambridge", moves: "f5f6e6f4e3c5g5"},
+    Opening {name: "Bat (Piau Continuation 2)", moves: "f5f6e6f4e3c5g5g3g4f3g6"},
+    Opening {name: "Melnikov, Bat (Piau Continuation 1)", moves: "f5f6e6f4e3c5g5g3g4f3c4"},
+    Opening {name: "Bat (Kling Continuation)", moves: "f5f6e6f4e3c5g5f3g4g6g3d6f7h5h4h3e7"},
+    Opening {name: "Bat (Kling Alternative)", moves: "f5f6e6f4e3c5g5d6g6"},
+    Opening {name: "Rose-v-Toth", moves: "f5f6e6f4e3c5c4"},
+    Opening {name: "Tanida", moves: "f5f6e6f4e3c5c4e7"},
+    Opening {name: "Aircraft, Feldborg", moves: "f5f6e6f4e3c5c4e7g4"},
+    Opening {name: "Sailboat", moves: "f5f6e6f4e3c5c4e7b5e2"},
+    Opening {name: "Maruoka", moves: "f5f6e6f4e3c5c4d3f3e2"},
+    Opening {name: "Landau", moves: "f5f6e6f4e3c5c4d3c3"},
+    Opening {name: "Buffalo, Kenichi Variation", moves: "f5f6e6f4c3"},
+    Opening {name: "Maruoka Buffalo", moves: "f5f6e6f4c3d7f3"},
+    Opening {name: "Tanida Buffalo", moves: "f5f6e6f4c3d6f3c4c5b4"},
+    Opening {name: "Hokuriku Buffalo", moves: "f5f6e6f4c3c4"},
+    Opening {name: "Wing Variation", moves: "f5f6d3f4"},
+    Opening {name: "Semi-Wing Variation", moves: "f5f6c4f4"},
+    Opening {name: "Parallel Opening", moves: "f5f4"},
+    Opening {name: "Perpendicular Opening", moves: "f5d6"},
+    Opening {name: "Mimura", moves: "f5d6c5f4e3d3"},
+    Opening {name: "Shaman, Danish", moves: "f5d6c5f4e3c6f3"},
+    Opening {name: "Inoue", moves: "f5d6c5f4e3c6e6"},
+    Opening {name: "IAGO", moves: "f5d6c5f4e3c6e6f6"},
+    Opening {name: "Bhagat", moves: "f5d6c5f4e3c6d7"},
+    Opening {name: "Rose", moves: "f5d6c5f4e3c6d3f6e6d7"},
+    Opening {name: "Flat", moves: "f5d6c5f4e3c6d3f6e6d7g4"},
+    Opening {name: "Rotating Flat", moves: "f5d6c5f4e3c6d3f6e6d7g4c4"},
+    Opening {name: "No-Kung", moves: "f5d6c4g5"},
+    Opening {name: "Comp'Oth", moves: "f5d6c4g5c6c5d7d3b4c3e3f3"},
+    Opening {name: "Tiger", moves: "f5d6c3d3c4"},
+    Opening {name: "Leader's Tiger", moves: "f5d6c3d3c4f4f6"},
+    Opening {name: "Ishii", moves: "f5d6c3d3c4f4c5b4b5c6f3e6e3g6f6g5d7g3"},
+    Opening {name: "Mainline Tiger", moves: "f5d6c3d3c4f4c5b3c2"},
+    Opening {name: "Tamenori", moves: "f5d6c3d3c4f4c5b3c2b4"},
+];
+
+// Symmetries that leave the starting position as it is; each one sends f5
+// to a different first move
+const ORIENTATIONS : [Symmetry; 4] = [
+    Symmetry::Identity,
+    Symmetry::Rotate180,
+    Symmetry::FlipDiagonal,
+    Symmetry::FlipAntiDiagonal,
+];
+
+// The other four swap the colors of the starting position, so they turn
+// games where White moves first into the usual lines
+const SWAPPED_ORIENTATIONS : [Symmetry; 4] = [
+    Symmetry::Rotate90,
+    Symmetry::Rotate270,
+    Symmetry::FlipVertical,
+    Symmetry::FlipHorizontal,
+];
+
+// Every line's squares, parsed the first time they're needed
+fn lines() -> &'static [(Vec<Loc>, &'static Opening)] {
+    static LINES: OnceLock<Vec<(Vec<Loc>, &'static Opening)>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        OPENINGS
+            .iter()
+            .map(|opening| (parse_moves(&bitboard::STANDARD, opening.moves).unwrap_or_default(), opening))
+            .collect()
+    })
+}
+
+// The longest named opening the game has followed so far. Games that don't
+// start from the standard position never match
+pub fn recognize(record: &GameRecord) -> Option<&'static Opening> {
+    let (start, start_player) = record.start();
+    let orientations = match start_player {
+        _ if *start != BoardStruct::new() => return None,
+        Player::Black => &ORIENTATIONS,
+        Player::White => &SWAPPED_ORIENTATIONS,
+        Player::Unknown => return None,
+    };
+    let played : Vec<Loc> = record.moves()
+        .iter()
+        .filter_map(|m| m.square)
+        .collect();
+    let first = *played.first()?;
+
+    // Turn the game around so that it starts with f5, like the book does
+    let g = &bitboard::STANDARD;
+    let f5 = notation::from_algebraic(g, "f5")?;
+    let orientation = orientations
+        .iter()
+        .find(|sym| sym.apply_square(g, first) == f5)?;
+    let played : Vec<Loc> = played
+        .iter()
+        .map(|sq| orientation.apply_square(g, *sq))
+        .collect();
+
+    lines()
+        .iter()
+        .filter(|(line, _)| !line.is_empty() && played.starts_with(line))
+        .max_by_key(|(line, _)| line.len())
+        .map(|(_, opening)| *opening)
+}
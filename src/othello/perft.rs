@@ -0,0 +1,38 @@
+// Counts the move sequences of a given length from a position, for checking
+// move generation against known numbers. Passes count as moves, and a game
+// that ends early counts once however many moves were left
+use crate::othello::*;
+use crate::othello::moves::*;
+
+pub fn perft(board: &BoardStruct, player: &Player, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let squares = legal_moves(player, board);
+    if squares.is_empty() {
+        return match next_player(board, player) {
+            Some(next) => perft(board, &next, depth - 1),
+            None => 1,
+        };
+    }
+
+    let mut child = *board;
+    squares
+        .iter()
+        .map(|sq| {
+            // Cheaper than cloning the board for every move
+            let flipped = make_move(sq, player, &mut child).unwrap();
+            let nodes = perft(&child, &player.opponent(), depth - 1);
+            unmake_move(&MoveRecord {player: *player, square: Some(*sq), flipped}, &mut child);
+            nodes
+        })
+        .sum()
+}
+
+// Node counts for every depth up to `depth`, from the start of a game on
+// `geometry`'s board
+pub fn counts(geometry: &'static Geometry, depth: u32) -> Vec<u64> {
+    let board = BoardStruct::initial(geometry);
+    (1..=depth).map(|d| perft(&board, &Player::Black, d)).collect()
+}
@@ -0,0 +1,214 @@
+// A game as a list of moves from a starting position, which can be stepped
+// backwards and forwards through
+use crate::othello::*;
+use crate::othello::moves::{
+    IllegalMoveError,
+    Loc,
+    MoveRecord,
+    any_legal_moves,
+    is_game_over,
+    make_move,
+    make_pass,
+    unmake_move,
+};
+
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    start: BoardStruct,
+    start_player: Player,
+    // Moves past `ply` have been undone and can be redone
+    moves: Vec<MoveRecord>,
+    ply: usize,
+    board: BoardStruct,
+    tomove: Player,
+}
+
+impl Default for GameRecord {
+    fn default() -> Self {
+        GameRecord::new()
+    }
+}
+
+impl GameRecord {
+    // A game on the standard board
+    pub fn new() -> Self {
+        GameRecord::from_position(BoardStruct::new(), Player::Black)
+    }
+
+    pub fn from_position(board: BoardStruct, tomove: Player) -> Self {
+        let tomove = if is_game_over(&tomove, &board) { Player::Unknown } else { tomove };
+        GameRecord {
+            start: board,
+            start_player: tomove,
+            moves: Vec::new(),
+            ply: 0,
+            board,
+            tomove,
+        }
+    }
+
+    // Like from_position, but turns down positions that can't be played
+    // from. If `tomove` has no moves the game opens with their pass
+    pub fn starting_from(board: BoardStruct, tomove: Player) -> Result<Self, String> {
+        if tomove == Player::Unknown {
+            return Err("Nobody is set to move".to_string());
+        }
+        // The middle four squares are always the first ones filled
+        if center(board.geometry()).iter().any(|sq| board.get(*sq) == Piece::EMPTY) {
+            return Err("The middle four squares must all have discs on them".to_string());
+        }
+
+        let mut game = GameRecord::from_position(board, tomove);
+        if game.is_over() {
+            return Err("Neither player has a move from this position".to_string());
+        }
+        if game.must_pass() {
+            game.pass().map_err(|ill| ill.to_string())?;
+        }
+        Ok(game)
+    }
+
+    pub fn start(&self) -> (&BoardStruct, &Player) {
+        (&self.start, &self.start_player)
+    }
+
+    pub fn board(&self) -> &BoardStruct {
+        &self.board
+    }
+
+    // Player::Unknown once the game is over. The player to move may have
+    // no legal moves, in which case they have to pass
+    pub fn tomove(&self) -> Player {
+        self.tomove
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.tomove == Player::Unknown
+    }
+
+    pub fn must_pass(&self) -> bool {
+        !self.is_over() && !any_legal_moves(&self.tomove, &self.board)
+    }
+
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    // The moves leading to the current position, not counting undone ones
+    pub fn moves(&self) -> &[MoveRecord] {
+        &self.moves[..self.ply]
+    }
+
+    pub fn last_move(&self) -> Option<&MoveRecord> {
+        self.moves().last()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.ply < self.moves.len()
+    }
+
+    // Playing a new move throws away anything that could have been redone
+    pub fn play(&mut self, square: Loc) -> Result<&MoveRecord, IllegalMoveError> {
+        let player = self.tomove;
+        let flipped = make_move(&square, &player, &mut self.board)?;
+        self.push(MoveRecord {player, square: Some(square), flipped});
+        Ok(&self.moves[self.ply - 1])
+    }
+
+    pub fn pass(&mut self) -> Result<&MoveRecord, IllegalMoveError> {
+        let record = make_pass(&self.tomove, &self.board)?;
+        self.push(record);
+        Ok(&self.moves[self.ply - 1])
+    }
+
+    pub fn undo(&mut self) -> Option<&MoveRecord> {
+        if self.ply == 0 {
+            return None;
+        }
+        self.ply -= 1;
+        let record = &self.moves[self.ply];
+        unmake_move(record, &mut self.board);
+        self.tomove = record.player;
+        Some(record)
+    }
+
+    pub fn redo(&mut self) -> Option<&MoveRecord> {
+        if !self.can_redo() {
+            return None;
+        }
+        let record = self.moves[self.ply].clone();
+        if let Some(sq) = record.square {
+            // Redone moves were legal the first time, so they still are
+            make_move(&sq, &record.player, &mut self.board).ok()?;
+        }
+        self.ply += 1;
+        self.update_tomove(&record.player);
+        Some(&self.moves[self.ply - 1])
+    }
+
+    // Undoes or redoes until `ply` moves have been played. Returns false if
+    // there aren't that many moves to redo
+    pub fn replay_to(&mut self, ply: usize) -> bool {
+        if ply > self.moves.len() {
+            return false;
+        }
+        while self.ply > ply {
+            self.undo();
+        }
+        while self.ply < ply {
+            self.redo();
+        }
+        true
+    }
+
+    // Every position from the start up to the current one, along with
+    // the player to move in it
+    pub fn positions(&self) -> Positions<'_> {
+        Positions {
+            moves: self.moves().iter(),
+            next: Some((self.start, self.start_player)),
+        }
+    }
+
+    fn push(&mut self, record: MoveRecord) {
+        self.moves.truncate(self.ply);
+        let player = record.player;
+        self.moves.push(record);
+        self.ply += 1;
+        self.update_tomove(&player);
+    }
+
+    fn update_tomove(&mut self, prev_player: &Player) {
+        self.tomove = if is_game_over(prev_player, &self.board) {
+            Player::Unknown
+        } else {
+            prev_player.opponent()
+        };
+    }
+}
+
+pub struct Positions<'a> {
+    moves: std::slice::Iter<'a, MoveRecord>,
+    next: Option<(BoardStruct, Player)>,
+}
+
+impl<'a> Iterator for Positions<'a> {
+    type Item = (BoardStruct, Player);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        if let Some(record) = self.moves.next() {
+            let mut board = current.0;
+            if let Some(sq) = record.square {
+                make_move(&sq, &record.player, &mut board).ok()?;
+            }
+            let tomove = if is_game_over(&record.player, &board) {
+                Player::Unknown
+            } else {
+                record.player.opponent()
+            };
+            self.next = Some((board, tomove));
+        }
+        Some(current)
+    }
+}
@@ -0,0 +1,104 @@
+// Draws boards as standalone SVG images, for putting positions on web pages.
+// Columns are labelled a, b, c... along the top and rows 1, 2, 3... down the
+// left, same as algebraic notation
+use crate::othello::*;
+use crate::othello::moves::Loc;
+
+const BOARD_COLOR : &str = "#2e7d32";
+const LINE_COLOR : &str = "#1b4d1e";
+const LAST_MOVE_COLOR : &str = "#e53935";
+
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    // Marked with a red dot
+    pub last_move: Option<Loc>,
+    // Marked with faint dots, usually the moves of the player to move
+    pub legal_moves: Vec<Loc>,
+    // Width of one square, in pixels
+    pub square_size: u32,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            last_move: None,
+            legal_moves: Vec::new(),
+            square_size: 40,
+        }
+    }
+}
+
+pub fn svg(board: &BoardStruct, options: &SvgOptions) -> String {
+    let g = board.geometry();
+    let sq = options.square_size.max(1) as f32;
+    // Room for the labels on every side, so the board sits in the middle
+    let margin = sq * 0.6;
+    let side = g.size as f32 * sq;
+    let total = side + 2.0 * margin;
+    // Middle of a square, in pixels from the top left of the image
+    let center = |square: Loc| -> (f32, f32) {
+        let (row, col) = (square / g.width() - 1, square % g.width() - 1);
+        (margin + (col as f32 + 0.5) * sq, margin + (row as f32 + 0.5) * sq)
+    };
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">",
+        total
+    );
+    out.push_str(&format!(
+        "<rect x=\"{0}\" y=\"{0}\" width=\"{1}\" height=\"{1}\" fill=\"{2}\" stroke=\"{3}\" stroke-width=\"2\"/>",
+        margin, side, BOARD_COLOR, LINE_COLOR
+    ));
+    for i in 1..g.size {
+        let at = margin + i as f32 * sq;
+        out.push_str(&format!(
+            "<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"{3}\"/><line x1=\"{1}\" y1=\"{0}\" x2=\"{2}\" y2=\"{0}\" stroke=\"{3}\"/>",
+            at, margin, margin + side, LINE_COLOR
+        ));
+    }
+
+    let font_size = sq * 0.4;
+    for i in 0..g.size {
+        let along = margin + (i as f32 + 0.5) * sq;
+        out.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+            along, margin / 2.0, font_size, (b'a' + i as u8) as char
+        ));
+        out.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+            margin / 2.0, along, font_size, i + 1
+        ));
+    }
+
+    for square in g.legal_spaces() {
+        let fill = match board.get(square) {
+            Piece::BLACK => "#000",
+            Piece::WHITE => "#fff",
+            _ => continue,
+        };
+        let (x, y) = center(square);
+        out.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"#000\"/>",
+            x, y, sq * 0.42, fill
+        ));
+    }
+    // Squares from outside might not be on the board
+    let on_board = |square: &Loc| g.square_to_bit(*square).is_some();
+    for square in options.legal_moves.iter().filter(|square| on_board(square)) {
+        let (x, y) = center(*square);
+        out.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#000\" fill-opacity=\"0.25\"/>",
+            x, y, sq * 0.12
+        ));
+    }
+    if let Some(square) = options.last_move.filter(on_board) {
+        let (x, y) = center(square);
+        out.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+            x, y, sq * 0.1, LAST_MOVE_COLOR
+        ));
+    }
+
+    out.push_str("</svg>");
+    out
+}
@@ -0,0 +1,92 @@
+// How a game came out, and why
+use serde::{Serialize, Deserialize};
+
+use crate::othello::*;
+use crate::othello::variant::Variant;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    #[serde(rename = "black")]
+    BlackWin,
+    #[serde(rename = "white")]
+    WhiteWin,
+    #[serde(rename = "draw")]
+    Draw,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum EndReason {
+    // Neither side could move
+    #[serde(rename = "normal")]
+    Normal,
+    #[serde(rename = "timeout")]
+    Timeout,
+    #[serde(rename = "illegal_move")]
+    IllegalMove,
+    // The AI process died or talked nonsense
+    #[serde(rename = "crash")]
+    Crash,
+    // The AI went over one of its resource limits
+    #[serde(rename = "resource_limit")]
+    ResourceLimit,
+    #[serde(rename = "resignation")]
+    Resignation,
+    #[serde(rename = "disconnect")]
+    Disconnect,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub outcome: Outcome,
+    pub reason: EndReason,
+    // Discs on the board when the game stopped
+    pub black_discs: u32,
+    pub white_discs: u32,
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Player {
+        match self {
+            Outcome::BlackWin => Player::Black,
+            Outcome::WhiteWin => Player::White,
+            Outcome::Draw => Player::Unknown,
+        }
+    }
+}
+
+impl From<&Player> for Outcome {
+    fn from(winner: &Player) -> Self {
+        match winner {
+            Player::Black => Outcome::BlackWin,
+            Player::White => Outcome::WhiteWin,
+            Player::Unknown => Outcome::Draw,
+        }
+    }
+}
+
+impl GameResult {
+    // A game played out to the end, decided by the discs
+    pub fn finished(board: &BoardStruct, variant: &Variant) -> Self {
+        GameResult::new(Outcome::from(&variant.winner(board)), EndReason::Normal, board)
+    }
+
+    // `loser` stopped playing for `reason`, which hands the game to the
+    // other side whatever the board says
+    pub fn forfeit(loser: &Player, reason: EndReason, board: &BoardStruct) -> Self {
+        GameResult::new(Outcome::from(&loser.opponent()), reason, board)
+    }
+
+    fn new(outcome: Outcome, reason: EndReason, board: &BoardStruct) -> Self {
+        let (black, white) = board.bitboards(&Player::Black);
+        GameResult {
+            outcome,
+            reason,
+            black_discs: black.count_ones(),
+            white_discs: white.count_ones(),
+        }
+    }
+
+    pub fn is_forfeit(&self) -> bool {
+        self.reason != EndReason::Normal
+    }
+}
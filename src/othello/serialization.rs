@@ -10,7 +10,7 @@ impl Serialize for BoardStruct {
         S: Serializer,
     {
         // String implements FromIterator<char>, str does not
-        let st : String = self.board
+        let st : String = self.to_mailbox()
             .iter()
             .map(|p: &Piece| -> char { p.into() })
             .collect();
@@ -24,28 +24,14 @@ impl<'de> Visitor<'de> for BoardVisitor {
     type Value = BoardStruct;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("an 8x8 Othello board in TJHSST notation (length 100)")
+        formatter.write_str("an Othello board in TJHSST notation")
     }
 
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        if value.len() != 100 {
-            return Err(E::custom(format!("board string has incorrect size!")));
-        }
-
-        let pcs : Vec<Piece> = value
-            .chars()
-            .map(|c: char| c.into())
-            .collect();
-        if pcs.len() != 100 {
-            return Err(E::custom(format!("something went wonky; board is not length 100 somewhere")));
-        }
-        
-        let mut board : Board = [Piece::OUTER; 100];
-        board.copy_from_slice(pcs.as_slice());
-        Ok(BoardStruct {board: board})
+        value.parse().map_err(E::custom)
     }
 }
 
@@ -0,0 +1,143 @@
+// The eight ways to rotate and reflect a board that keep it a legal board
+use crate::othello::*;
+use crate::othello::bitboard::{Bitboard, Geometry};
+use crate::othello::moves::Loc;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Symmetry {
+    Identity,
+    // Clockwise
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    // Top row swaps with the bottom row
+    FlipVertical,
+    // Left column swaps with the right column
+    FlipHorizontal,
+    // Reflect over the a1-h8 diagonal
+    FlipDiagonal,
+    // Reflect over the h1-a8 diagonal
+    FlipAntiDiagonal,
+}
+
+pub const SYMMETRIES : [Symmetry; 8] = [
+    Symmetry::Identity, Symmetry::Rotate90, Symmetry::Rotate180, Symmetry::Rotate270,
+    Symmetry::FlipVertical, Symmetry::FlipHorizontal, Symmetry::FlipDiagonal, Symmetry::FlipAntiDiagonal,
+];
+
+// The standard board has fast bit tricks for these, other sizes go square
+// by square
+fn flip_vertical (b: u64) -> u64 {
+    b.swap_bytes()
+}
+
+fn flip_horizontal (b: u64) -> u64 {
+    const K1 : u64 = 0x5555_5555_5555_5555;
+    const K2 : u64 = 0x3333_3333_3333_3333;
+    const K4 : u64 = 0x0f0f_0f0f_0f0f_0f0f;
+    let b = ((b >> 1) & K1) | ((b & K1) << 1);
+    let b = ((b >> 2) & K2) | ((b & K2) << 2);
+    ((b >> 4) & K4) | ((b & K4) << 4)
+}
+
+// Swaps rows and columns
+fn flip_diagonal (b: u64) -> u64 {
+    const K1 : u64 = 0x5500_5500_5500_5500;
+    const K2 : u64 = 0x3333_0000_3333_0000;
+    const K4 : u64 = 0x0f0f_0f0f_0000_0000;
+    let mut b = b;
+    let mut t = K4 & (b ^ (b << 28));
+    b ^= t ^ (t >> 28);
+    t = K2 & (b ^ (b << 14));
+    b ^= t ^ (t >> 14);
+    t = K1 & (b ^ (b << 7));
+    b ^= t ^ (t >> 7);
+    b
+}
+
+impl Symmetry {
+    fn apply_standard (&self, b: u64) -> u64 {
+        match self {
+            Symmetry::Identity => b,
+            Symmetry::Rotate90 => flip_horizontal(flip_diagonal(b)),
+            Symmetry::Rotate180 => flip_vertical(flip_horizontal(b)),
+            Symmetry::Rotate270 => flip_vertical(flip_diagonal(b)),
+            Symmetry::FlipVertical => flip_vertical(b),
+            Symmetry::FlipHorizontal => flip_horizontal(b),
+            Symmetry::FlipDiagonal => flip_diagonal(b),
+            Symmetry::FlipAntiDiagonal => flip_vertical(flip_horizontal(flip_diagonal(b))),
+        }
+    }
+
+    // Where the square at (row, col) ends up
+    fn apply_coords (&self, size: usize, row: usize, col: usize) -> (usize, usize) {
+        let last = size - 1;
+        match self {
+            Symmetry::Identity => (row, col),
+            Symmetry::Rotate90 => (col, last - row),
+            Symmetry::Rotate180 => (last - row, last - col),
+            Symmetry::Rotate270 => (last - col, row),
+            Symmetry::FlipVertical => (last - row, col),
+            Symmetry::FlipHorizontal => (row, last - col),
+            Symmetry::FlipDiagonal => (col, row),
+            Symmetry::FlipAntiDiagonal => (last - col, last - row),
+        }
+    }
+
+    pub fn apply (&self, geometry: &Geometry, b: Bitboard) -> Bitboard {
+        if geometry.size == 8 {
+            return self.apply_standard(b as u64) as Bitboard;
+        }
+        let size = geometry.size;
+        let mut out = 0;
+        let mut rest = b & geometry.all;
+        while rest != 0 {
+            let bit = rest.trailing_zeros() as usize;
+            rest &= rest - 1;
+            let (row, col) = self.apply_coords(size, bit / size, bit % size);
+            out |= 1 << (row * size + col);
+        }
+        out
+    }
+
+    // Squares off the board stay where they are
+    pub fn apply_square (&self, geometry: &Geometry, square: Loc) -> Loc {
+        match geometry.square_to_bit(square) {
+            Some(bit) => geometry.bit_to_square(self.apply(geometry, 1 << bit).trailing_zeros()),
+            None => square,
+        }
+    }
+
+    // The symmetry that undoes this one
+    pub fn inverse (&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => *other,
+        }
+    }
+
+    pub fn apply_board (&self, board: &BoardStruct) -> BoardStruct {
+        let g = board.geometry();
+        let (black, white) = board.bitboards(&Player::Black);
+        BoardStruct::from_bitboards(g, self.apply(g, black), self.apply(g, white))
+    }
+}
+
+pub fn symmetries (board: &BoardStruct) -> [BoardStruct; 8] {
+    let mut boards = [*board; 8];
+    for (i, sym) in SYMMETRIES.iter().enumerate() {
+        boards[i] = sym.apply_board(board);
+    }
+    boards
+}
+
+// The smallest of the symmetric versions of a board, and the symmetry
+// that turns the board into it
+pub fn canonical (board: &BoardStruct) -> (BoardStruct, Symmetry) {
+    SYMMETRIES
+        .iter()
+        .map(|sym| (sym.apply_board(board), *sym))
+        .min_by_key(|(bd, _)| bd.bitboards(&Player::Black))
+        .unwrap()
+}
@@ -0,0 +1,64 @@
+// The rules a room plays by: how big the board is, and whether the player
+// with the fewest discs wins instead (anti-reversi)
+use serde::{Serialize, Deserialize};
+
+use crate::othello::*;
+
+fn default_size() -> usize {
+    8
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    #[serde(default = "default_size")]
+    pub size: usize,
+    #[serde(default)]
+    pub anti: bool,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant {
+            size: default_size(),
+            anti: false,
+        }
+    }
+}
+
+impl Variant {
+    pub fn new(size: usize, anti: bool) -> Result<Self, String> {
+        let variant = Variant {size, anti};
+        variant.check()?;
+        Ok(variant)
+    }
+
+    // Variants come straight from clients, so this has to be checked before
+    // anything relies on the size
+    pub fn check(&self) -> Result<(), String> {
+        match Geometry::for_size(self.size) {
+            Some(_) => Ok(()),
+            None => {
+                let sizes : Vec<String> = bitboard::SIZES.iter().map(|size| size.to_string()).collect();
+                Err(format!("boards can be {} squares across, not {}", sizes.join(", "), self.size))
+            }
+        }
+    }
+
+    pub fn geometry(&self) -> Option<&'static Geometry> {
+        Geometry::for_size(self.size)
+    }
+
+    pub fn is_standard(&self) -> bool {
+        *self == Variant::default()
+    }
+
+    // Who won a finished game on `board`
+    pub fn winner(&self, board: &BoardStruct) -> Player {
+        let winner = moves::winner(board);
+        if self.anti {
+            winner.opponent()
+        } else {
+            winner
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Reading and writing WTHOR database files (.wtb), the format of the
+// French federation's game archive. Players and tournaments are stored as
+// ids into separate .JOU and .TRN files, which aren't handled here.
+//
+// A file is a 16 byte header followed by 68 byte games. Moves are stored in
+// TJHSST numbering already, with passes left out.
+use crate::othello::*;
+use crate::othello::record::GameRecord;
+
+pub const HEADER_LEN : usize = 16;
+pub const GAME_LEN : usize = 68;
+const MOVES_LEN : usize = 60;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct WthorHeader {
+    // Date the file was made
+    pub created: (u16, u8, u8),
+    // Year the games were played
+    pub year: u16,
+    // Depth of the perfect-play search used for `theoretical` scores
+    pub depth: u8,
+}
+
+#[derive(Clone, Debug)]
+pub struct WthorGame {
+    pub tournament: u16,
+    pub black: u16,
+    pub white: u16,
+    // Number of black discs at the end of the game
+    pub black_discs: u8,
+    // Black's disc count with perfect play from `depth` empties on
+    pub theoretical: u8,
+    pub record: GameRecord,
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+pub fn read_wthor(bytes: &[u8]) -> Result<(WthorHeader, Vec<WthorGame>), String> {
+    if bytes.len() < HEADER_LEN {
+        return Err("file is too short to have a WTHOR header".to_string());
+    }
+    let count = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    if bytes[12] != 0 && bytes[12] != 8 {
+        return Err(format!("only 8x8 games are supported, not {}x{}", bytes[12], bytes[12]));
+    }
+    if bytes.len() < HEADER_LEN + count * GAME_LEN {
+        return Err(format!("header promises {} games but the file is too short", count));
+    }
+
+    let header = WthorHeader {
+        created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+        year: read_u16(&bytes[10..12]),
+        depth: bytes[14],
+    };
+
+    let games = bytes[HEADER_LEN..HEADER_LEN + count * GAME_LEN]
+        .chunks(GAME_LEN)
+        .enumerate()
+        .map(|(i, game)| read_game(game).map_err(|why| format!("game {}: {}", i, why)))
+        .collect::<Result<Vec<WthorGame>, String>>()?;
+
+    Ok((header, games))
+}
+
+fn read_game(bytes: &[u8]) -> Result<WthorGame, String> {
+    let mut record = GameRecord::new();
+    for sq in bytes[8..].iter().take_while(|sq| **sq != 0) {
+        if record.must_pass() {
+            record.pass().map_err(|why| format!("{:?}", why))?;
+        }
+        record.play(*sq as usize).map_err(|_| format!("illegal move {}", sq))?;
+    }
+
+    Ok(WthorGame {
+        tournament: read_u16(&bytes[0..2]),
+        black: read_u16(&bytes[2..4]),
+        white: read_u16(&bytes[4..6]),
+        black_discs: bytes[6],
+        theoretical: bytes[7],
+        record,
+    })
+}
+
+// Only games from the standard starting position can be written
+pub fn write_wthor(header: &WthorHeader, games: &[WthorGame]) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + games.len() * GAME_LEN);
+    let (year, month, day) = header.created;
+    bytes.extend_from_slice(&[(year / 100) as u8, (year % 100) as u8, month, day]);
+    bytes.extend_from_slice(&(games.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes.extend_from_slice(&header.year.to_le_bytes());
+    bytes.extend_from_slice(&[8, 0, header.depth, 0]);
+
+    for (i, game) in games.iter().enumerate() {
+        // This also rules out other board sizes
+        if game.record.start() != (&BoardStruct::new(), &Player::Black) {
+            return Err(format!("game {} doesn't start from the standard position", i));
+        }
+        bytes.extend_from_slice(&game.tournament.to_le_bytes());
+        bytes.extend_from_slice(&game.black.to_le_bytes());
+        bytes.extend_from_slice(&game.white.to_le_bytes());
+        bytes.extend_from_slice(&[game.black_discs, game.theoretical]);
+
+        let mut squares : Vec<u8> = game.record.moves()
+            .iter()
+            .filter_map(|m| m.square)
+            .map(|sq| sq as u8)
+            .collect();
+        squares.resize(MOVES_LEN, 0);
+        bytes.extend_from_slice(&squares);
+    }
+    Ok(bytes)
+}
+
+impl WthorGame {
+    // Fills in the disc counts from the final position of the record.
+    // Empty squares go to the winner, as in tournament scoring
+    pub fn new(tournament: u16, black: u16, white: u16, record: GameRecord) -> Self {
+        let (own, opp) = record.board().bitboards(&Player::Black);
+        let (discs, opp_discs) = (own.count_ones() as u8, opp.count_ones() as u8);
+        let empties = record.board().geometry().area() as u8 - discs - opp_discs;
+        let black_discs = if discs > opp_discs {
+            discs + empties
+        } else if discs == opp_discs {
+            discs + empties / 2
+        } else {
+            discs
+        };
+        WthorGame {
+            tournament,
+            black,
+            white,
+            black_discs,
+            theoretical: black_discs,
+            record,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Balanced 8-move openings, for AI matches that don't play out the same way
+// every time. The published XOT list can be loaded from a file, and there's
+// a list built in for when it isn't around
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::othello::{bitboard, Player};
+use crate::othello::notation::parse_moves;
+use crate::othello::record::GameRecord;
+use crate::othello::symmetry::canonical;
+
+// Plays out an opening from the usual start on the standard board
+pub fn opening_game(line: &str) -> Result<GameRecord, String> {
+    let mut game = GameRecord::new();
+    for square in parse_moves(&bitboard::STANDARD, line)? {
+        if game.must_pass() {
+            game.pass().map_err(|ill| ill.to_string())?;
+        }
+        game.play(square).map_err(|ill| ill.to_string())?;
+    }
+    Ok(game)
+}
+
+// Reads a list in the format of the XOT files: one opening per line, moves
+// written one after another. Every line has to play out legally, and no two
+// can end in the same position turned around, or some openings would come up
+// more often than the rest
+pub fn parse_openings(text: &str) -> Result<Vec<GameRecord>, String> {
+    let mut seen = HashSet::new();
+    let mut openings = Vec::new();
+    for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let game = opening_game(line).map_err(|why| format!("{}: {}", line, why))?;
+        let (board, _) = canonical(game.board());
+        if !seen.insert((board.bitboards(&Player::Black), game.tomove() == Player::Black)) {
+            return Err(format!("{}: the same opening as an earlier line, turned around", line));
+        }
+        openings.push(game);
+    }
+    Ok(openings)
+}
+
+// The 3000 8-move openings, up to symmetry, that a depth 4 search with
+// eval::heuristic scores closest to even. That's how the XOT list was made
+// too, with a much stronger program, but this isn't that list
+const BUILTIN : &str = include_str!("xot.txt");
+
+pub fn builtin_openings() -> Result<Vec<GameRecord>, String> {
+    parse_openings(BUILTIN).map_err(|why| format!("Bad builtin opening: {}", why))
+}
+
+pub fn load_openings(path: &Path) -> Result<Vec<GameRecord>, String> {
+    let text = fs::read_to_string(path).map_err(|why| format!("Couldn't read openings from {}: {}", path.display(), why))?;
+    parse_openings(&text).map_err(|why| format!("Bad opening in {}: {}", path.display(), why))
+}
+
+pub fn random_opening<R: Rng>(openings: &[GameRecord], rng: &mut R) -> Result<GameRecord, String> {
+    openings.choose(rng).cloned().ok_or_else(|| "There are no openings to pick from".to_string())
+}
@@ -1,6 +1,8 @@
 pub mod structs;
 pub mod actions;
 pub mod urls;
+pub mod squares;
 
 // Re-export structs
 pub use structs::*;
+pub use squares::*;
@@ -0,0 +1,198 @@
+// How squares are written on the wire. Messages are built with TJHSST
+// numbering and converted to each client's chosen format just before sending
+use serde::{Serialize, Deserialize};
+
+use crate::othello::Player;
+use crate::othello::bitboard::Geometry;
+use crate::othello::moves::{Loc, MoveRecord};
+use crate::othello::notation;
+use crate::protocol::structs::ServerMessage;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SquareFormat {
+    // Mailbox index, 11..88 on the standard board
+    #[serde(rename = "tjhsst")]
+    #[default]
+    Tjhsst,
+    // 0..63 on the standard board, across each row from the top left
+    #[serde(rename = "index")]
+    Index,
+    // a1..h8 on the standard board
+    #[serde(rename = "algebraic")]
+    Algebraic,
+}
+
+// A client's square format, along with the board it's used on. Only TJHSST
+// numbers mean the same thing whatever the board size
+#[derive(Copy, Clone, Debug)]
+pub struct Notation {
+    pub format: SquareFormat,
+    pub geometry: &'static Geometry,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Square {
+    Number(usize),
+    Name(String),
+}
+
+// A move as sent to clients
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayedMove {
+    pub player: Player,
+    // None for a pass
+    pub square: Option<Square>,
+    pub flipped: Vec<Square>,
+}
+
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Square::Number(sq) => write!(f, "{}", sq),
+            Square::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<Loc> for Square {
+    fn from(sq: Loc) -> Self {
+        Square::Number(sq)
+    }
+}
+
+impl From<&MoveRecord> for PlayedMove {
+    fn from(record: &MoveRecord) -> Self {
+        PlayedMove {
+            player: record.player,
+            square: record.square.map(Square::from),
+            flipped: record.flipped.iter().map(|sq| Square::from(*sq)).collect(),
+        }
+    }
+}
+
+impl Notation {
+    pub fn new(format: SquareFormat, geometry: &'static Geometry) -> Self {
+        Notation {format, geometry}
+    }
+
+    // `square` must be in TJHSST numbering, which is what Square::from gives
+    pub fn encode(&self, square: &Square) -> Square {
+        let sq = match square {
+            Square::Number(sq) => *sq,
+            Square::Name(_) => return square.clone(),
+        };
+        match self.format {
+            SquareFormat::Tjhsst => Square::Number(sq),
+            SquareFormat::Index => match notation::to_index(self.geometry, sq) {
+                Some(index) => Square::Number(index),
+                None => square.clone(),
+            },
+            SquareFormat::Algebraic => match notation::to_algebraic(self.geometry, sq) {
+                Some(name) => Square::Name(name),
+                None => square.clone(),
+            },
+        }
+    }
+
+    // Names are always read as algebraic, numbers according to the format
+    pub fn decode(&self, square: &Square) -> Result<Loc, String> {
+        match (self.format, square) {
+            (_, Square::Name(name)) => {
+                notation::from_algebraic(self.geometry, name).ok_or_else(|| format!("\"{}\" is not a square", name))
+            },
+            (SquareFormat::Index, Square::Number(index)) => {
+                notation::from_index(self.geometry, *index).ok_or_else(|| format!("{} is not a square index", index))
+            },
+            (_, Square::Number(sq)) => Ok(*sq),
+        }
+    }
+
+    fn encode_all(&self, squares: &[Square]) -> Vec<Square> {
+        squares.iter().map(|sq| self.encode(sq)).collect()
+    }
+
+    fn encode_move(&self, played: &PlayedMove) -> PlayedMove {
+        PlayedMove {
+            player: played.player,
+            square: played.square.as_ref().map(|sq| self.encode(sq)),
+            flipped: self.encode_all(&played.flipped),
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn in_format(&self, format: Notation) -> ServerMessage {
+        match self {
+            ServerMessage::GameSnapshot {board, tomove, black, white, start, start_player, variant, history} => {
+                ServerMessage::GameSnapshot {
+                    board: *board,
+                    tomove: *tomove,
+                    black: black.clone(),
+                    white: white.clone(),
+                    start: *start,
+                    start_player: *start_player,
+                    variant: *variant,
+                    history: history.iter().map(|played| format.encode_move(played)).collect(),
+                }
+            },
+            ServerMessage::MoveRequest {tomove, squares, timelimit} => {
+                ServerMessage::MoveRequest {
+                    tomove: *tomove,
+                    squares: format.encode_all(squares),
+                    timelimit: *timelimit,
+                }
+            },
+            ServerMessage::BoardUpdate {last_move, legal_moves, ..} => {
+                let mut update = self.clone();
+                if let ServerMessage::BoardUpdate {last_move: new_last, legal_moves: new_legal, ..} = &mut update {
+                    *new_last = last_move.as_ref().map(|played| format.encode_move(played));
+                    *new_legal = format.encode_all(legal_moves);
+                }
+                update
+            },
+            ServerMessage::IllegalMove {player, square, board, reason} => {
+                ServerMessage::IllegalMove {
+                    player: *player,
+                    square: square.as_ref().map(|sq| format.encode(sq)),
+                    board: *board,
+                    reason: *reason,
+                }
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::bitboard::{self, Geometry};
+
+    #[test]
+    fn formats_round_trip() {
+        for size in bitboard::SIZES.iter() {
+            let geometry = Geometry::for_size(*size).unwrap();
+            for format in [SquareFormat::Tjhsst, SquareFormat::Index, SquareFormat::Algebraic].iter() {
+                let notation = Notation::new(*format, geometry);
+                for sq in geometry.legal_spaces() {
+                    let encoded = notation.encode(&Square::from(sq));
+                    assert_eq!(notation.decode(&encoded), Ok(sq), "{:?} {}", format, encoded);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bad_squares() {
+        let standard = Notation::new(SquareFormat::Index, Geometry::for_size(8).unwrap());
+        assert!(standard.decode(&Square::Number(64)).is_err());
+        assert!(standard.decode(&Square::Name("i1".to_string())).is_err());
+        assert!(standard.decode(&Square::Name("a9".to_string())).is_err());
+        let small = Notation::new(SquareFormat::Algebraic, Geometry::for_size(6).unwrap());
+        assert!(small.decode(&Square::Name("g6".to_string())).is_err());
+        // Names are algebraic whatever the format
+        let tjhsst = Notation::new(SquareFormat::Tjhsst, Geometry::for_size(10).unwrap());
+        assert_eq!(tjhsst.decode(&Square::Name("j10".to_string())), Ok(130));
+    }
+}
@@ -1,7 +1,8 @@
-use futures_channel::mpsc::UnboundedSender;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use uuid::Uuid;
 // re-export
 pub type Id = Uuid;
@@ -11,14 +12,23 @@ pub type RoomMap = Arc<Mutex<HashMap<Id, Room>>>;
 
 // private module
 use crate::othello::*;
+use crate::othello::record::GameRecord;
+use crate::othello::eval::Evaluation;
+use crate::othello::moves::{IllegalMoveError, IllegalMoveReason};
+use crate::othello::result::GameResult;
+use crate::othello::variant::Variant;
+use crate::othello::xot;
+use crate::runner::settings;
+use crate::protocol::squares::*;
 
 #[derive(Clone, Debug)]
 pub struct Room {
-    pub id: Id,
     pub black_name: String,
     pub white_name: String,
     pub timelimit: f32,
     pub watching: Vec<Id>,
+    pub game: GameRecord,
+    pub variant: Variant,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,6 +36,8 @@ pub struct ExternalRoom {
     black: String,
     white: String,
     timelimit: f32,
+    #[serde(flatten)]
+    variant: Variant,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -33,12 +45,44 @@ pub struct ExternalRoom {
 pub enum ServerMessage {
     #[serde(rename = "list_reply")]
     ListReply {room_list: HashMap<Id, ExternalRoom>},
+    #[serde(rename = "game_snapshot")]
+    GameSnapshot {
+        board: BoardStruct, tomove: Player, black: String, white: String,
+        // Where the game started and the rules it's played by, so the
+        // history can be replayed from the right place
+        start: BoardStruct, start_player: Player, variant: Variant,
+        history: Vec<PlayedMove>,
+    },
     #[serde(rename = "board_update")]
-    BoardUpdate {board: BoardStruct, tomove: Player, black: String, white: String},
+    BoardUpdate {
+        board: BoardStruct, tomove: Player, black: String, white: String, opening: Option<String>,
+        // The move that led here, None at the start of the game. This is
+        // never a pass, those get their own message
+        last_move: Option<PlayedMove>,
+        // What `tomove` can play next
+        legal_moves: Vec<Square>,
+        black_discs: u32,
+        white_discs: u32,
+        // Only sent to watchers that ask for it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        eval: Option<Evaluation>,
+    },
     #[serde(rename = "move_request")]
-    MoveRequest {},
+    MoveRequest {tomove: Player, squares: Vec<Square>, timelimit: f32},
     #[serde(rename = "game_end")]
-    GameEnd {board: BoardStruct, winner: Player, forfeit: bool},
+    GameEnd {
+        board: BoardStruct, winner: Player, forfeit: bool, result: GameResult, opening: Option<String>,
+        // What exactly went wrong, if the loser forfeited
+        forfeit_reason: Option<String>,
+        // How every position of the game measured up, from the start
+        #[serde(default)]
+        report: Vec<Evaluation>,
+    },
+    // Sent when a player has no moves and their turn is skipped
+    #[serde(rename = "pass")]
+    Pass {player: Player},
+    #[serde(rename = "illegal_move")]
+    IllegalMove {player: Player, square: Option<Square>, board: BoardStruct, reason: IllegalMoveReason},
     #[serde(rename = "game_error")]
     GameError {error: String},
     #[serde(rename = "disconect")]
@@ -47,10 +91,71 @@ pub enum ServerMessage {
 
 // fiiine, we'll make these struct fields public
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct PlayRequest {pub black: String, pub white: String, pub t: f32}
+pub struct PlayRequest {
+    pub black: String,
+    pub white: String,
+    pub t: f32,
+    #[serde(default)]
+    pub format: SquareFormat,
+    // Start from here instead of the usual position, in TJHSST notation
+    // with or without the border
+    #[serde(default)]
+    pub board: Option<String>,
+    // Who moves first from `board`, Black if left out
+    #[serde(default)]
+    pub tomove: Option<Player>,
+    // Start from one of the balanced openings instead, picked with `seed`
+    // if there is one
+    #[serde(default)]
+    pub random_opening: bool,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // Once the game is over, play it again from the same start with the
+    // colors swapped
+    #[serde(default)]
+    pub swap: bool,
+    // Squares across the board, 6, 8 or 10
+    #[serde(default = "default_size")]
+    pub size: usize,
+    // Whoever ends up with fewer discs wins
+    #[serde(default)]
+    pub anti: bool,
+}
+
+fn default_size() -> usize {
+    Variant::default().size
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WatchRequest {
+    pub watching: Id,
+    #[serde(default)]
+    pub format: SquareFormat,
+    #[serde(default)]
+    pub eval: bool,
+}
 
+// A picture of a position, either given outright or the current one in a room.
+// Not a websocket request, it gets a plain HTTP response with the image
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct WatchRequest {pub watching: Id}
+pub struct RenderRequest {
+    // TJHSST notation with or without the border, the usual start if left out
+    #[serde(default)]
+    pub board: Option<String>,
+    #[serde(default)]
+    pub room: Option<Id>,
+    // Mark the last move. Numbers are read using `format`, names are algebraic
+    #[serde(default)]
+    pub last: Option<String>,
+    // Whose legal moves to mark, if anyone's. A room's player to move if
+    // `legal` is set without this
+    #[serde(default)]
+    pub tomove: Option<Player>,
+    #[serde(default)]
+    pub legal: bool,
+    #[serde(default)]
+    pub format: SquareFormat,
+}
 
 // TODO: potentially have optional fields on this, make into another enum?
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -62,13 +167,16 @@ pub enum ClientRequest {
     Play(PlayRequest),
     Watch(WatchRequest),
     List(ListRequest),
+    Render(RenderRequest),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
     #[serde(rename = "movereply")]
-    MoveReply {square: usize},
+    MoveReply {square: Square},
+    #[serde(rename = "resign")]
+    Resign {},
     #[serde(rename = "disconnect")]
     Disconnect {},
 }
@@ -79,6 +187,7 @@ impl From<Room> for ExternalRoom {
             black: r.black_name,
             white: r.white_name,
             timelimit: r.timelimit,
+            variant: r.variant,
         }
     }
 }
@@ -89,18 +198,103 @@ impl From<&Room> for ExternalRoom {
             black: r.black_name.clone(),
             white: r.white_name.clone(),
             timelimit: r.timelimit,
+            variant: r.variant,
         }
     }
 }
 
+// Read the first time somebody asks for a random opening. A file that can't
+// be read isn't remembered, so it can be fixed without a restart
+fn openings() -> Result<&'static [GameRecord], String> {
+    static OPENINGS: OnceLock<Vec<GameRecord>> = OnceLock::new();
+    if let Some(openings) = OPENINGS.get() {
+        return Ok(openings);
+    }
+    let openings = match settings::xot_file() {
+        Some(path) => xot::load_openings(&path)?,
+        None => xot::builtin_openings()?,
+    };
+    Ok(OPENINGS.get_or_init(|| openings))
+}
+
 impl PlayRequest {
-    pub fn to_room(self, id: &Id) -> Room {
+    pub fn variant(&self) -> Result<Variant, String> {
+        Variant::new(self.size, self.anti)
+    }
+
+    // The game as it stands before anybody moves
+    pub fn start(&self) -> Result<GameRecord, String> {
+        let variant = self.variant()?;
+        let geometry = variant.geometry().unwrap();
+        if self.random_opening {
+            if self.board.is_some() {
+                return Err("Can't start from both a board and a random opening".to_string());
+            }
+            if geometry.size != 8 {
+                return Err(format!("Random openings are only for 8x8 boards, not {}x{}", geometry.size, geometry.size));
+            }
+            let mut rng = match self.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            return xot::random_opening(openings()?, &mut rng);
+        }
+
+        let board = match &self.board {
+            Some(board) => BoardStruct::parse_sized(board, geometry.size).map_err(|why| why.to_string())?,
+            None => BoardStruct::initial(geometry),
+        };
+        let tomove = self.tomove.unwrap_or(Player::Black);
+        GameRecord::starting_from(board, tomove)
+    }
+
+    pub fn into_room(self, game: GameRecord, variant: Variant) -> Room {
         Room {
-            id: id.clone(),
             black_name: self.black,
             white_name: self.white,
             timelimit: self.t,
             watching: Vec::new(),
+            game,
+            variant,
+        }
+    }
+}
+
+impl Room {
+    // Everything a watcher needs to catch up on a game in progress
+    pub fn snapshot(&self) -> ServerMessage {
+        let (start, start_player) = self.game.start();
+        ServerMessage::GameSnapshot {
+            board: *self.game.board(),
+            tomove: self.game.tomove(),
+            black: self.black_name.clone(),
+            white: self.white_name.clone(),
+            start: *start,
+            start_player: *start_player,
+            variant: self.variant,
+            history: self.game.moves().iter().map(PlayedMove::from).collect(),
+        }
+    }
+}
+
+impl ServerMessage {
+    // Drops the evaluation from board updates, for clients that didn't ask
+    // for it
+    pub fn without_eval(mut self) -> ServerMessage {
+        if let ServerMessage::BoardUpdate {eval, ..} = &mut self {
+            *eval = None;
+        }
+        self
+    }
+}
+
+impl From<&IllegalMoveError> for ServerMessage {
+    fn from(ill: &IllegalMoveError) -> Self {
+        ServerMessage::IllegalMove {
+            player: ill.player,
+            square: ill.square.map(Square::from),
+            board: ill.board,
+            reason: ill.reason,
         }
     }
 }
@@ -113,6 +307,6 @@ impl From<WatchRequest> for Id {
 
 impl From<&WatchRequest> for Id {
     fn from(wrq: &WatchRequest) -> Self {
-        wrq.watching.clone()
+        wrq.watching
     }
 }
@@ -1,6 +1,6 @@
 use crate::protocol::{
     ClientRequest,
-    PlayRequest, WatchRequest, ListRequest,
+    PlayRequest, WatchRequest, ListRequest, RenderRequest,
 };
 use http::{Uri};
 use log::*;
@@ -10,10 +10,7 @@ use serde_urlencoded::de::Error;
 use serde::de::Error as SerdeError;
 
 pub fn parse_uri(uri: Uri) -> Result<ClientRequest, Error> {
-    let query: &str = match uri.query() {
-        Some(s) => s,
-        None => &"",
-    };
+    let query: &str = uri.query().unwrap_or_default();
     debug!("query received: {}", query);
 
     match uri.path() {
@@ -29,6 +26,10 @@ pub fn parse_uri(uri: Uri) -> Result<ClientRequest, Error> {
             let req : ListRequest = serde_urlencoded::from_str(query)?;
             Ok(ClientRequest::List(req))
         },
+        "/render" => {
+            let req : RenderRequest = serde_urlencoded::from_str(query)?;
+            Ok(ClientRequest::Render(req))
+        },
         other_path => Err(Error::custom(format!("Unknown path {}", other_path)))
     }
 }
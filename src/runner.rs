@@ -1,5 +1,7 @@
 use log::*;
 use serde_json::error::{Error as SerdeError};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::io::{
     AsyncBufReadExt,
     AsyncReadExt,
@@ -9,21 +11,73 @@ use tokio::io::{
     Error as IOError,
     ErrorKind as IOErrorKind,
 };
-use tokio::stream::StreamExt;
-use tokio::time::{timeout, Duration};
+use tokio::sync::Notify;
+use tokio::time::{timeout, timeout_at, Duration, Instant};
+
+use tokio::process::{ChildStderr, Command};
 
 pub mod structs;
 pub mod settings;
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod jail;
+pub mod limits;
+pub mod backend;
+mod subprocess;
+mod builtin;
+mod remote;
+mod nboard;
 // Re-export structs
 pub use structs::*;
+pub use backend::Backend;
+use limits::{Limit, Limits};
 
 use crate::othello::{
     BoardStruct,
     Player,
+    ai::Strategy,
+    variant::Variant,
 };
 
-pub fn make_runner(ai_name: &String) -> IOResult<Runner> {
-    let mut command = settings::build_jailed_command(ai_name)?;
+// The backend that plays as `name`: one of the builtin strategies, a native
+// program, a remote bot, an NBoard engine, or otherwise one of the python
+// AIs. It still has to be started
+pub fn make_backend(name: &String, variant: &Variant) -> IOResult<Box<dyn Backend>> {
+    if let Some(strategy) = name.strip_prefix(settings::BUILTIN_PREFIX) {
+        return match strategy.parse::<Strategy>() {
+            Ok(strategy) => Ok(Box::new(builtin::Builtin::new(strategy, *variant))),
+            Err(why) => Err(IOError::new(IOErrorKind::NotFound, why.as_str())),
+        };
+    }
+    if let Some(program) = name.strip_prefix(settings::NATIVE_PREFIX) {
+        let limits = settings::runner_limits(name)?;
+        let command = settings::build_native_command(program, limits)?;
+        return Ok(Box::new(subprocess::Subprocess::new(name, command, limits)));
+    }
+    if let Some(bot) = name.strip_prefix(settings::REMOTE_PREFIX) {
+        let url = settings::remote_bot_url(bot)?;
+        return Ok(Box::new(remote::Remote::new(&url)));
+    }
+    if let Some(engine) = name.strip_prefix(settings::NBOARD_PREFIX) {
+        if variant.anti {
+            return Err(IOError::new(IOErrorKind::InvalidInput, "NBoard engines can't play anti-reversi"));
+        }
+        // The protocol has no way to say how big the board is
+        if variant.size != 8 {
+            return Err(IOError::new(IOErrorKind::InvalidInput, format!(
+                "NBoard engines only play on 8x8 boards, not {}x{}", variant.size, variant.size,
+            )));
+        }
+        let limits = settings::runner_limits(name)?;
+        let command = settings::build_nboard_command(engine, limits)?;
+        return Ok(Box::new(nboard::Nboard::new(engine, command, limits)));
+    }
+    let limits = settings::runner_limits(name)?;
+    let command = settings::build_jailed_command(name, limits)?;
+    Ok(Box::new(subprocess::Subprocess::new(name, command, limits)))
+}
+
+// `limits` should be the ones `command` was built with
+pub fn make_runner(ai_name: &str, mut command: Command, limits: Limits) -> IOResult<Runner> {
     let mut child = command.spawn()?;
     match (child.stdin.take(), child.stdout.take(), child.stderr.take()) {
         (None, _, _) => {
@@ -36,17 +90,45 @@ pub fn make_runner(ai_name: &String) -> IOResult<Runner> {
             Err(IOError::new(IOErrorKind::BrokenPipe, "Could not open stderr on subprocess!"))
         },
         (Some(stdin), Some(stdout), Some(stderr)) => {
+            let output_read = Arc::new(AtomicU64::new(0));
+            let said = Arc::new(Mutex::new(String::new()));
+            let over_limit = Arc::new(Notify::new());
+            let stderr_task = tokio::spawn(drain_stderr(stderr, limits.output_bytes, output_read.clone(), said.clone(), over_limit.clone()));
             Ok(Runner {
-                child: child,
-                stdin: stdin,
-                stdout: BufReader::new(stdout).lines(),
-                stderr: BufReader::new(stderr),
-                ai_name: ai_name.clone(),
+                child,
+                stdin,
+                stdout: BufReader::new(stdout),
+                ai_name: ai_name.to_string(),
+                limits,
+                output_read,
+                stderr: said,
+                stderr_task: Some(stderr_task),
+                over_limit,
             })
         }
     }
 }
 
+// Reads stderr until it closes, keeping what fits in the output limit.
+// Past the limit it gives up and tells `over_limit`, leaving the runner to
+// be killed
+async fn drain_stderr(mut stderr: ChildStderr, limit: u64, output_read: Arc<AtomicU64>, said: Arc<Mutex<String>>, over_limit: Arc<Notify>) {
+    let mut buf = [0; 4096];
+    loop {
+        let read = match stderr.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => read,
+        };
+        let before = output_read.fetch_add(read as u64, Ordering::Relaxed);
+        let kept = limit.saturating_sub(before).min(read as u64) as usize;
+        said.lock().unwrap().push_str(&String::from_utf8_lossy(&buf[..kept]));
+        if before + read as u64 > limit {
+            over_limit.notify();
+            return;
+        }
+    }
+}
+
 fn serialize_request(board: &BoardStruct, player: &Player, timelimit: f32, ai_name: &String) -> Result<String, SerdeError> {
     let mut board_str = serde_json::to_string(board)?;
     let mut player_str = serde_json::to_string(player)?;
@@ -73,32 +155,69 @@ pub async fn get_move(runner: &mut Runner, board: &BoardStruct, player: &Player,
     runner.stdin.write_all(to_send.as_bytes()).await?;
 
     let timeout_fut = timeout(
-        // Add 1 sec to timeout here to account for overhead of communication
-        Duration::from_millis((timelimit * 1000.0 + 1000.0) as u64),
-        runner.stdout.next()
+        // Add a bit to timeout here to account for overhead of communication
+        Duration::from_millis((timelimit * 1000.0) as u64) + backend::GRACE,
+        read_reply(runner)
     );
     // TODO: add some way to time out this await in case the JailedRunner hangs for whatever reason
     match timeout_fut.await {
-        Ok(Some(reply)) => {
-            let reply = reply?;
+        Ok(Ok(Some(reply))) => {
             log::debug!("Got line \"{}\" from subprocess", &reply);
-            let square : Result<usize, SerdeError> = serde_json::from_str(&reply);
-            if let Err(why) = square {
-                return Err(why.into());
+            let square : Result<usize, SerdeError> = serde_json::from_str(reply.trim());
+            match square {
+                Ok(square) => Ok(square),
+                // Say what the runner actually sent, it's usually a stray
+                // print statement
+                Err(why) => Err(IOError::new(
+                    IOErrorKind::InvalidData,
+                    format!("runner replied {:?}, which is not a square: {}", reply, why),
+                )),
             }
-            let square : usize = square.unwrap();
-            
-            Ok(square)
-        },
-        Ok(None) => {
-            Err(IOError::new(IOErrorKind::BrokenPipe, "Stream ended when trying to read reply from runner!"))
         },
+        Ok(Ok(None)) => Err(died(runner).await),
+        Ok(Err(why)) => Err(why),
         Err(_) => {
             Err(IOError::new(IOErrorKind::TimedOut, "Stream timed out when trying to read reply from runner!"))
         }
     }
 }
 
+// One line from the runner, without the newline, or None once it's gone.
+// Counts against the output limit, as does anything on stderr meanwhile
+async fn read_reply(runner: &mut Runner) -> IOResult<Option<String>> {
+    let limit = runner.limits.output_bytes;
+    let exceeded = runner.limits.exceeded(Limit::Output);
+    let mut reply = Vec::new();
+    // One byte more than it's allowed, to tell hitting the limit from going over
+    let budget = limit.saturating_sub(runner.output_read.load(Ordering::Relaxed)) + 1;
+    let mut stdout = (&mut runner.stdout).take(budget);
+    let read = tokio::select! {
+        read = stdout.read_until(b'\n', &mut reply) => read?,
+        _ = runner.over_limit.notified() => return Err(exceeded.into()),
+    };
+    if runner.output_read.fetch_add(read as u64, Ordering::Relaxed) + read as u64 > limit {
+        return Err(exceeded.into());
+    }
+    if read == 0 {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&reply).trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+// Why the runner stopped talking: the limit it ran into, if that's what
+// happened, or else just that it's gone
+async fn died(runner: &mut Runner) -> IOError {
+    // It should be on its way out, give it a moment. What CPU time it used
+    // is gone once it's reaped, so that goes first
+    let cpu = timeout(Duration::from_millis(500), limits::cpu_time_at_exit(runner.child.id())).await.ok().flatten();
+    let status = timeout(Duration::from_millis(500), &mut runner.child).await.ok().and_then(Result::ok);
+    let status = status.filter(|_| settings::jailed());
+    match runner.limits.violation(status, cpu, runner.output_read.load(Ordering::Relaxed)) {
+        Some(exceeded) => exceeded.into(),
+        None => IOError::new(IOErrorKind::BrokenPipe, "Stream ended when trying to read reply from runner!"),
+    }
+}
+
 pub async fn kill_and_get_error(mut runner: Runner) -> IOResult<String> {
     // Can't use the wait_with_output command here because we have taken the
     // stream away from the child object.
@@ -107,18 +226,32 @@ pub async fn kill_and_get_error(mut runner: Runner) -> IOResult<String> {
     info!("Attempting to stop process {}", runner.child.id());
     // First, send special command to process telling it to stop
     runner.stdin.write_all(b"stop\n").await?;
+    wait_and_get_error(runner).await
+}
 
-    // Then, close stdin so the process
+// Everything after asking the runner to stop, which depends on what it speaks
+async fn wait_and_get_error(mut runner: Runner) -> IOResult<String> {
+    // Close stdin so the process
     // If it tries to read again, should error out and quit anyway
     debug!("Manually dropping stdin");
     drop(runner.stdin);
 
-    // Wait for child process to terminate. Equivalent to `.join()` in python
+    // Wait for child process to terminate. Equivalent to `.join()` in python.
+    // One that takes too long, or that's over the output limit and stuck
+    // writing to a full pipe, is killed
+    let deadline = Instant::now() + settings::RUNNER_STOP_TIME;
+    let over_limit = runner.output_read.load(Ordering::Relaxed) > runner.limits.output_bytes;
+    if over_limit || timeout_at(deadline, &mut runner.child).await.is_err() {
+        warn!("Killing process {}, which didn't stop", runner.child.id());
+        let _ = runner.child.kill();
+    }
     runner.child.await?;
 
-    // Read all stderr, return it
-    let mut error_output = String::new();
-    runner.stderr.read_to_string(&mut error_output).await?;
-
-    Ok(error_output)
+    // And for the last of stderr, unless something it left behind is
+    // holding it open
+    if let Some(task) = runner.stderr_task.take() {
+        let _ = timeout_at(deadline, task).await;
+    }
+    let said = runner.stderr.lock().unwrap();
+    Ok(said.clone())
 }
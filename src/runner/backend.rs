@@ -0,0 +1,54 @@
+// Everything the server can get moves from besides the client on the
+// socket: python AIs, native programs, the builtin strategies and bots
+// hosted elsewhere. Games only deal with this trait, not with each kind
+use futures::future::BoxFuture;
+use std::fmt;
+use std::io::Result as IOResult;
+use tokio::time::{Duration, Instant};
+
+use crate::othello::{BoardStruct, Player};
+use crate::othello::moves::MoveRecord;
+
+// Extra time given to programs and bots on top of the time limit, for the
+// overhead of talking to them
+pub const GRACE : Duration = Duration::from_secs(1);
+
+// Marks a player giving up, so it can be told apart from other errors. It
+// says who, since the human on the socket can resign on a program's turn
+#[derive(Debug)]
+pub struct Resigned(pub Player);
+
+impl fmt::Display for Resigned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Player resigned")
+    }
+}
+
+impl std::error::Error for Resigned {}
+
+// No async fns in traits, so these hand back boxed futures instead
+pub trait Backend: Send {
+    // Gets ready to play, e.g. by starting a program or connecting to a bot.
+    // Called once, before anything else
+    fn start(&mut self) -> BoxFuture<'_, IOResult<()>>;
+
+    // A move for `player`, which has to be in by `deadline`. Timing out is
+    // an error of kind TimedOut, giving up is one wrapping Resigned, and
+    // anything else going wrong is the backend's own fault
+    fn get_move<'a>(&'a mut self, board: &'a BoardStruct, player: Player, deadline: Instant) -> BoxFuture<'a, IOResult<usize>>;
+
+    // A move or pass it didn't make itself, as soon as it's played. Only
+    // backends that keep track of the game need to care
+    fn notify<'a>(&'a mut self, _played: &'a MoveRecord) -> BoxFuture<'a, IOResult<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    // Shuts it down, handing back whatever it said along the way that might
+    // explain how things went, e.g. a program's stderr
+    fn stop(self: Box<Self>) -> BoxFuture<'static, IOResult<String>>;
+}
+
+// How long there is until `deadline`, in the seconds the runner protocols use
+pub fn seconds_left(deadline: Instant) -> f32 {
+    deadline.saturating_duration_since(Instant::now()).as_secs_f32()
+}
@@ -0,0 +1,96 @@
+// The strategies in othello::ai, played without starting any programs
+use futures::future::BoxFuture;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::task::spawn_blocking;
+use tokio::time::{timeout_at, Instant};
+
+use crate::othello::{BoardStruct, Player};
+use crate::othello::ai::Strategy;
+use crate::othello::variant::Variant;
+use super::backend::{self, Backend};
+
+pub struct Builtin {
+    strategy: Strategy,
+    // Builtin players need to know which way round winning goes
+    variant: Variant,
+}
+
+impl Builtin {
+    pub fn new(strategy: Strategy, variant: Variant) -> Self {
+        Builtin {strategy, variant}
+    }
+}
+
+// Stops a search when dropped. Dropping the handle to a blocking task
+// doesn't stop it, so without this a search that timed out, or whose game
+// went away, would hold on to its thread until it finished
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Backend for Builtin {
+    fn start(&mut self) -> BoxFuture<'_, IOResult<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn get_move<'a>(&'a mut self, board: &'a BoardStruct, player: Player, deadline: Instant) -> BoxFuture<'a, IOResult<usize>> {
+        let (strategy, anti, board) = (self.strategy, self.variant.anti, *board);
+        Box::pin(async move {
+            let stop = Arc::new(AtomicBool::new(false));
+            let _stop = StopOnDrop(stop.clone());
+            // Searching can take a while, so it's done off of the async threads
+            let mut search = {
+                let stop = stop.clone();
+                spawn_blocking(move || strategy.choose_move(&board, &player, anti, &stop))
+            };
+
+            // Searches go as deep as they can in the time, so at the deadline
+            // it's told to stop and hand over the best move it has
+            let found = match timeout_at(deadline, &mut search).await {
+                Ok(found) => Ok(found),
+                Err(_) => {
+                    stop.store(true, Ordering::Relaxed);
+                    timeout_at(deadline + backend::GRACE, search).await
+                },
+            };
+            match found {
+                Ok(Ok(Some(square))) => Ok(square),
+                Ok(Ok(None)) => Err(IOError::new(IOErrorKind::InvalidData, "Builtin player was asked to move with no legal moves!")),
+                Ok(Err(why)) => Err(IOError::other(format!("Builtin player crashed: {}", why))),
+                Err(_) => Err(IOError::new(IOErrorKind::TimedOut, "Builtin player ran out of time!")),
+            }
+        })
+    }
+
+    fn stop(self: Box<Self>) -> BoxFuture<'static, IOResult<String>> {
+        Box::pin(async { Ok(String::new()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Duration;
+
+    use crate::othello::moves::is_legal;
+
+    // Far too deep to finish in the time, so it has to settle for a
+    // shallower search's move rather than run out of time
+    #[tokio::test]
+    async fn deep_search_moves_in_time() {
+        let variant = Variant::new(10, false).unwrap();
+        let board = BoardStruct::initial(variant.geometry().unwrap());
+        let mut builtin = Builtin::new(Strategy::AlphaBeta(10), variant);
+
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let square = builtin.get_move(&board, Player::Black, deadline).await.unwrap();
+        assert!(Instant::now() < deadline + backend::GRACE);
+        assert!(is_legal(&square, &Player::Black, &board));
+    }
+}
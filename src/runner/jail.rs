@@ -0,0 +1,552 @@
+// Locks AI processes away from the rest of the machine, Linux only. Each one
+// gets its own user, mount, PID, network, IPC and UTS namespaces, so it runs
+// as nobody, can't see other processes, and has no network at all. Its
+// filesystem is an empty read-only root with a few host directories bound
+// in read-only, a private /tmp, and just enough of /dev for python. On top of
+// that every capability is dropped and a seccomp filter blocks the syscalls
+// that could be used to break back out.
+//
+// All of this happens between fork and exec, where allocating isn't safe, so
+// everything the child needs is worked out up front by Jail::new
+use std::ffi::CString;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+
+use libc::{c_int, c_ulong, sock_filter};
+
+// The jail's root is put together at NEW_ROOT, on a scratch tmpfs mounted
+// over /tmp in the jail's mount namespace, with the host moved to OLD_ROOT.
+// The host's own /tmp is never touched
+const NEW_ROOT : &str = "/newroot";
+const OLD_ROOT : &str = "/oldroot";
+const HOSTNAME : &str = "othello";
+// Device files python expects to be able to open
+const DEVICES : [&str; 4] = ["/dev/null", "/dev/zero", "/dev/random", "/dev/urandom"];
+// Inside the jail the AI is nobody
+const JAIL_ID : u32 = 65534;
+
+// Syscalls that only make sense for breaking out, or poking at the kernel
+const DENIED_SYSCALLS : [libc::c_long; 42] = [
+    libc::SYS_ptrace, libc::SYS_process_vm_readv, libc::SYS_process_vm_writev,
+    libc::SYS_mount, libc::SYS_umount2, libc::SYS_pivot_root, libc::SYS_chroot,
+    libc::SYS_open_tree, libc::SYS_move_mount, libc::SYS_fsopen, libc::SYS_fsconfig,
+    libc::SYS_fsmount, libc::SYS_fspick, libc::SYS_mount_setattr,
+    libc::SYS_unshare, libc::SYS_setns,
+    libc::SYS_kexec_load, libc::SYS_kexec_file_load, libc::SYS_reboot,
+    libc::SYS_init_module, libc::SYS_finit_module, libc::SYS_delete_module,
+    libc::SYS_swapon, libc::SYS_swapoff, libc::SYS_quotactl, libc::SYS_acct,
+    libc::SYS_bpf, libc::SYS_perf_event_open, libc::SYS_userfaultfd,
+    libc::SYS_keyctl, libc::SYS_add_key, libc::SYS_request_key,
+    libc::SYS_open_by_handle_at, libc::SYS_name_to_handle_at,
+    libc::SYS_settimeofday, libc::SYS_clock_settime, libc::SYS_adjtimex,
+    libc::SYS_syslog, libc::SYS_fanotify_init,
+    libc::SYS_io_uring_setup, libc::SYS_io_uring_enter, libc::SYS_io_uring_register,
+];
+
+// Making new namespaces with clone is blocked like unshare is
+const NAMESPACE_FLAGS : c_int = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID
+    | libc::CLONE_NEWNET | libc::CLONE_NEWIPC | libc::CLONE_NEWUTS | libc::CLONE_NEWCGROUP;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH : u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH : u32 = 0xc000_00b7;
+
+// Offsets into struct seccomp_data
+const SECCOMP_NR : u32 = 0;
+const SECCOMP_ARCH : u32 = 4;
+const SECCOMP_ARG0 : u32 = 16;
+
+// What to put at a path inside the jail
+enum Entry {
+    Dir,
+    File,
+    // Merged /usr systems have /bin -> usr/bin and the like
+    Symlink(CString),
+}
+
+struct Bind {
+    // The host path as seen from OLD_ROOT, and where it goes under NEW_ROOT
+    source: CString,
+    target: CString,
+    // Directories to make first, outermost first
+    parents: Vec<CString>,
+    entry: Entry,
+    // Device files can't go on a nodev mount
+    device: bool,
+}
+
+pub struct Jail {
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+    binds: Vec<Bind>,
+    tmp_options: CString,
+    workdir: CString,
+    last_cap: c_int,
+    filter: Vec<sock_filter>,
+}
+
+fn cstring(bytes: &[u8]) -> IOResult<CString> {
+    CString::new(bytes).map_err(|_| IOError::new(IOErrorKind::InvalidInput, "jail paths can't contain NUL bytes"))
+}
+
+fn under(prefix: &str, path: &Path) -> IOResult<CString> {
+    let mut full = prefix.as_bytes().to_vec();
+    full.extend_from_slice(path.as_os_str().as_bytes());
+    cstring(&full)
+}
+
+impl Bind {
+    // Missing paths are skipped, so one list of paths works across distros
+    fn new(path: &Path, device: bool) -> IOResult<Option<Bind>> {
+        if !path.is_absolute() {
+            return Err(IOError::new(IOErrorKind::InvalidInput, format!("jail path {} must be absolute", path.display())));
+        }
+        let meta = match std::fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(ref why) if why.kind() == IOErrorKind::NotFound => return Ok(None),
+            Err(why) => return Err(why),
+        };
+        let entry = if meta.file_type().is_symlink() {
+            Entry::Symlink(cstring(std::fs::read_link(path)?.as_os_str().as_bytes())?)
+        } else if meta.is_dir() {
+            Entry::Dir
+        } else {
+            Entry::File
+        };
+
+        let mut parents = path.ancestors()
+            .skip(1)
+            .filter(|parent| parent.parent().is_some())
+            .map(|parent| under(NEW_ROOT, parent))
+            .collect::<IOResult<Vec<CString>>>()?;
+        parents.reverse();
+        Ok(Some(Bind {
+            source: under(OLD_ROOT, path)?,
+            target: under(NEW_ROOT, path)?,
+            parents,
+            entry,
+            device,
+        }))
+    }
+}
+
+impl Jail {
+    // `readonly` are the host paths the AI can see, `workdir` is where it
+    // starts, and it gets bound in read-only too
+    pub fn new(readonly: &[PathBuf], workdir: &Path, tmp_size: &str) -> IOResult<Jail> {
+        let mut binds = Vec::new();
+        for path in readonly.iter().map(|path| path.as_path()).chain(std::iter::once(workdir)) {
+            binds.extend(Bind::new(path, false)?);
+        }
+        for device in DEVICES.iter() {
+            binds.extend(Bind::new(Path::new(device), true)?);
+        }
+
+        // Capabilities past the last one the kernel knows about can't be
+        // dropped, but aren't a problem either
+        let last_cap = std::fs::read_to_string("/proc/sys/kernel/cap_last_cap")
+            .ok()
+            .and_then(|last| last.trim().parse().ok())
+            .unwrap_or(63);
+
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Ok(Jail {
+            uid_map: format!("{} {} 1\n", JAIL_ID, uid).into_bytes(),
+            gid_map: format!("{} {} 1\n", JAIL_ID, gid).into_bytes(),
+            binds,
+            tmp_options: cstring(format!("mode=1777,size={}", tmp_size).as_bytes())?,
+            workdir: cstring(workdir.as_os_str().as_bytes())?,
+            last_cap,
+            filter: seccomp_filter(),
+        })
+    }
+
+    // Makes `cmd` start inside the jail
+    pub fn confine(self, cmd: &mut StdCommand) {
+        unsafe {
+            cmd.pre_exec(move || self.enter());
+        }
+    }
+
+    // Runs in the child, between fork and exec
+    fn enter(&self) -> IOResult<()> {
+        check(unsafe { libc::unshare(NAMESPACE_FLAGS) }, "making namespaces")?;
+        write_file("/proc/self/setgroups\0", b"deny")?;
+        write_file("/proc/self/uid_map\0", &self.uid_map)?;
+        write_file("/proc/self/gid_map\0", &self.gid_map)?;
+
+        // Only children go into the new PID namespace, so the AI has to be
+        // one. This process stays outside, waiting to pass on its exit status,
+        // which the namespace's init sends back over a pipe
+        let mut status_pipe = [0; 2];
+        check(unsafe { libc::pipe2(status_pipe.as_mut_ptr(), libc::O_CLOEXEC) }, "making the status pipe")?;
+        let [status_read, status_write] = status_pipe;
+        let pid = check(unsafe { libc::fork() }, "forking into the PID namespace")?;
+        if pid != 0 {
+            wait_for_jail(pid, status_read);
+        }
+        unsafe { libc::close(status_read) };
+        // Killing the process the server knows about takes the AI with it
+        prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL as c_ulong, "setting the parent death signal")?;
+
+        self.build_root()?;
+        check(unsafe { libc::sethostname(HOSTNAME.as_ptr() as *const libc::c_char, HOSTNAME.len()) }, "setting the hostname")?;
+
+        // This process is the namespace's init, which the kernel won't send
+        // signals like SIGXCPU to, so the AI has to be another fork down
+        let pid = check(unsafe { libc::fork() }, "forking the AI")?;
+        if pid != 0 {
+            run_init(pid, status_write);
+        }
+        check(unsafe { libc::chdir(self.workdir.as_ptr()) }, "changing to the AI directory")?;
+        self.drop_privileges()
+    }
+
+    fn build_root(&self) -> IOResult<()> {
+        // Nothing done in here should show up on the host
+        mount(None, "/\0", None, libc::MS_REC | libc::MS_PRIVATE, None, "making mounts private")?;
+
+        // Move the host out of the way to OLD_ROOT, with a scratch tmpfs as
+        // the root while the real one is built at NEW_ROOT
+        mount(Some("tmpfs\0"), "/tmp\0", Some("tmpfs\0"), libc::MS_NOSUID | libc::MS_NODEV, Some("mode=0755\0"), "mounting the scratch root")?;
+        check(unsafe { libc::chdir(c("/tmp\0")) }, "changing to the scratch root")?;
+        make_dir("newroot\0")?;
+        make_dir("oldroot\0")?;
+        pivot_root("/tmp\0", "/tmp/oldroot\0")?;
+        check(unsafe { libc::chdir(c("/\0")) }, "changing to the scratch root")?;
+
+        mount(Some("tmpfs\0"), "/newroot\0", Some("tmpfs\0"), libc::MS_NOSUID | libc::MS_NODEV, Some("mode=0755\0"), "mounting the new root")?;
+        // Before the binds, so it doesn't hide any of them that are under /tmp
+        make_dir("/newroot/tmp\0")?;
+        check(unsafe {
+            libc::mount(c("tmpfs\0"), c("/newroot/tmp\0"), c("tmpfs\0"), libc::MS_NOSUID | libc::MS_NODEV, self.tmp_options.as_ptr() as *const libc::c_void)
+        }, "mounting /tmp")?;
+        for bind in self.binds.iter() {
+            for parent in bind.parents.iter() {
+                make_dir_c(parent)?;
+            }
+            match &bind.entry {
+                Entry::Symlink(dest) => {
+                    check(unsafe { libc::symlink(dest.as_ptr(), bind.target.as_ptr()) }, "linking a jail path")?;
+                    continue;
+                },
+                Entry::Dir => make_dir_c(&bind.target)?,
+                Entry::File => {
+                    let fd = check(unsafe { libc::open(bind.target.as_ptr(), libc::O_CREAT | libc::O_WRONLY | libc::O_CLOEXEC, 0o644) }, "making a jail file")?;
+                    unsafe { libc::close(fd) };
+                },
+            }
+            check(unsafe {
+                libc::mount(bind.source.as_ptr(), bind.target.as_ptr(), std::ptr::null(), libc::MS_BIND, std::ptr::null())
+            }, "binding a host path")?;
+            // Flags the host mount had can't be taken away in a user
+            // namespace, so they have to be asked for again
+            let mut flags = libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY | libc::MS_NOSUID | locked_flags(&bind.target);
+            if !bind.device {
+                flags |= libc::MS_NODEV;
+            }
+            check(unsafe {
+                libc::mount(std::ptr::null(), bind.target.as_ptr(), std::ptr::null(), flags, std::ptr::null())
+            }, "making a host path read-only")?;
+        }
+
+        // multiprocessing keeps its locks in /dev/shm, and wants /proc. The
+        // PID namespace means /proc only shows the AI's own processes
+        make_dir("/newroot/dev\0")?;
+        make_dir("/newroot/dev/shm\0")?;
+        check(unsafe {
+            libc::mount(c("tmpfs\0"), c("/newroot/dev/shm\0"), c("tmpfs\0"), libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC, self.tmp_options.as_ptr() as *const libc::c_void)
+        }, "mounting /dev/shm")?;
+        make_dir("/newroot/proc\0")?;
+        mount(Some("proc\0"), "/newroot/proc\0", Some("proc\0"), libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC, None, "mounting /proc")?;
+
+        mount(None, "/newroot\0", None, libc::MS_REMOUNT | libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV, None, "making the new root read-only")?;
+
+        // Swap in the new root, then drop the scratch one along with the
+        // host underneath it
+        check(unsafe { libc::chdir(c("/newroot\0")) }, "changing to the new root")?;
+        pivot_root(".\0", ".\0")?;
+        check(unsafe { libc::umount2(c(".\0"), libc::MNT_DETACH) }, "unmounting the host")?;
+        check(unsafe { libc::chdir(c("/\0")) }, "changing to the new root")?;
+        Ok(())
+    }
+
+    fn drop_privileges(&self) -> IOResult<()> {
+        check(unsafe {
+            libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_CLEAR_ALL as c_ulong, 0 as c_ulong, 0 as c_ulong, 0 as c_ulong)
+        }, "clearing ambient capabilities")?;
+        for cap in 0..=self.last_cap {
+            prctl(libc::PR_CAPBSET_DROP, cap as c_ulong, "dropping a bounding capability")?;
+        }
+        let header = CapHeader {version: LINUX_CAPABILITY_VERSION_3, pid: 0};
+        let data = [CapData::default(); 2];
+        check(unsafe { libc::syscall(libc::SYS_capset, &header, data.as_ptr()) as c_int }, "dropping capabilities")?;
+        prctl(libc::PR_SET_NO_NEW_PRIVS, 1, "setting no_new_privs")?;
+
+        let program = libc::sock_fprog {
+            len: self.filter.len() as u16,
+            filter: self.filter.as_ptr() as *mut sock_filter,
+        };
+        check(unsafe {
+            libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER as c_ulong, &program as *const libc::sock_fprog)
+        }, "installing the seccomp filter")?;
+        Ok(())
+    }
+}
+
+// Closes every fd but `keep`, then waits for `pid` to exit, reaping any
+// other children along the way. The std pipe that reports exec failures is
+// among the fds, and spawn waits for every copy of it to close
+fn close_and_wait(pid: libc::pid_t, keep: c_int) -> c_int {
+    let below = keep == 0 || unsafe { libc::syscall(libc::SYS_close_range, 0, keep - 1, 0) } == 0;
+    let above = unsafe { libc::syscall(libc::SYS_close_range, keep + 1, u32::MAX, 0) } == 0;
+    if !(below && above) {
+        for fd in (0..1024).filter(|fd| *fd != keep) {
+            unsafe { libc::close(fd) };
+        }
+    }
+
+    let mut status = 0;
+    loop {
+        let waited = unsafe { libc::waitpid(-1, &mut status, 0) };
+        if waited == pid {
+            return status;
+        }
+        if waited < 0 && IOError::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            unsafe { libc::_exit(1) };
+        }
+    }
+}
+
+// Init inside the PID namespace. Reaps whatever the AI leaves behind until
+// the AI exits, then writes its wait status to `status_fd` and exits.
+// Everything else in the namespace goes with it. Never returns
+fn run_init(pid: libc::pid_t, status_fd: c_int) -> ! {
+    let status = close_and_wait(pid, status_fd);
+    unsafe {
+        libc::write(status_fd, &status as *const c_int as *const libc::c_void, std::mem::size_of::<c_int>());
+        libc::_exit(0)
+    }
+}
+
+// Stays outside the PID namespace until its init exits, then exits the way
+// the AI did, so the server can tell what happened. If init never got as far
+// as sending the AI's status, e.g. because setting up the jail failed, this
+// exits the way init did instead. Never returns
+fn wait_for_jail(pid: libc::pid_t, status_fd: c_int) -> ! {
+    let init_status = close_and_wait(pid, status_fd);
+    let mut status : c_int = 0;
+    let size = std::mem::size_of::<c_int>();
+    let read = loop {
+        let read = unsafe { libc::read(status_fd, &mut status as *mut c_int as *mut libc::c_void, size) };
+        if read >= 0 || IOError::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            break read;
+        }
+    };
+    if read != size as isize {
+        status = init_status;
+    }
+
+    if libc::WIFSIGNALED(status) {
+        let sig = libc::WTERMSIG(status);
+        // Die of the same signal, without leaving a core file behind
+        let no_core = libc::rlimit {rlim_cur: 0, rlim_max: 0};
+        unsafe {
+            libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+            libc::signal(sig, libc::SIG_DFL);
+            libc::kill(libc::getpid(), sig);
+        }
+    }
+    unsafe { libc::_exit(libc::WEXITSTATUS(status)) }
+}
+
+// Kernel structs for capset, which libc doesn't have
+const LINUX_CAPABILITY_VERSION_3 : u32 = 0x2008_0522;
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+fn bpf_stmt(code: u32, k: u32) -> sock_filter {
+    sock_filter {code: code as u16, jt: 0, jf: 0, k}
+}
+
+fn bpf_jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+    sock_filter {code: code as u16, jt, jf, k}
+}
+
+fn seccomp_filter() -> Vec<sock_filter> {
+    use libc::{BPF_ABS, BPF_JEQ, BPF_JMP, BPF_JSET, BPF_K, BPF_LD, BPF_RET, BPF_W};
+    let deny = |errno: c_int| bpf_stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ERRNO | errno as u32);
+    let mut filter = vec![
+        // Syscall numbers mean different things on other architectures
+        bpf_stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_ARCH),
+        bpf_jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+        bpf_stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        bpf_stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_NR),
+    ];
+    // Same goes for the x32 ones
+    #[cfg(target_arch = "x86_64")]
+    {
+        filter.push(bpf_jump(BPF_JMP | libc::BPF_JGE | BPF_K, 0x4000_0000, 0, 1));
+        filter.push(bpf_stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS));
+    }
+    for nr in DENIED_SYSCALLS.iter() {
+        filter.push(bpf_jump(BPF_JMP | BPF_JEQ | BPF_K, *nr as u32, 0, 1));
+        filter.push(deny(libc::EPERM));
+    }
+    // clone3 hides its flags behind a pointer, so pretend it doesn't exist
+    // and libc falls back to clone, whose flags can be checked
+    filter.push(bpf_jump(BPF_JMP | BPF_JEQ | BPF_K, libc::SYS_clone3 as u32, 0, 1));
+    filter.push(deny(libc::ENOSYS));
+    filter.push(bpf_jump(BPF_JMP | BPF_JEQ | BPF_K, libc::SYS_clone as u32, 0, 3));
+    filter.push(bpf_stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_ARG0));
+    filter.push(bpf_jump(BPF_JMP | BPF_JSET | BPF_K, NAMESPACE_FLAGS as u32, 0, 1));
+    filter.push(deny(libc::EPERM));
+    filter.push(bpf_stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW));
+    filter
+}
+
+// The mount flags on `path` that a user namespace isn't allowed to clear
+fn locked_flags(path: &CString) -> c_ulong {
+    const FLAGS : [(c_ulong, c_ulong); 6] = [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ];
+    let mut stats : libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return 0;
+    }
+    FLAGS.iter()
+        .filter(|(st, _)| stats.f_flag & st != 0)
+        .fold(0, |flags, (_, ms)| flags | ms)
+}
+
+// Helpers that don't allocate, for use between fork and exec. Strings passed
+// in have to end with a NUL already
+
+fn c(s: &str) -> *const libc::c_char {
+    debug_assert!(s.ends_with('\0'));
+    s.as_ptr() as *const libc::c_char
+}
+
+// Reports which step failed on stderr, since the error that makes it back
+// to spawn is only an errno
+fn check<T: Into<i64> + Copy>(ret: T, step: &str) -> IOResult<T> {
+    if ret.into() >= 0 {
+        return Ok(ret);
+    }
+    let err = IOError::last_os_error();
+    for part in ["jail: ", step, " failed\n"].iter() {
+        unsafe { libc::write(2, part.as_ptr() as *const libc::c_void, part.len()) };
+    }
+    Err(err)
+}
+
+fn mount(source: Option<&str>, target: &str, fstype: Option<&str>, flags: c_ulong, data: Option<&str>, step: &str) -> IOResult<()> {
+    let ptr = |s: Option<&str>| s.map(c).unwrap_or(std::ptr::null());
+    check(unsafe {
+        libc::mount(ptr(source), c(target), ptr(fstype), flags, ptr(data) as *const libc::c_void)
+    }, step).map(|_| ())
+}
+
+fn prctl(option: c_int, arg: c_ulong, step: &str) -> IOResult<()> {
+    check(unsafe { libc::prctl(option, arg, 0 as c_ulong, 0 as c_ulong, 0 as c_ulong) }, step).map(|_| ())
+}
+
+fn pivot_root(new_root: &str, put_old: &str) -> IOResult<()> {
+    check(unsafe { libc::syscall(libc::SYS_pivot_root, c(new_root), c(put_old)) }, "pivoting root").map(|_| ())
+}
+
+fn make_dir(path: &str) -> IOResult<()> {
+    make_dir_ptr(c(path))
+}
+
+fn make_dir_c(path: &CString) -> IOResult<()> {
+    make_dir_ptr(path.as_ptr())
+}
+
+// Directories that are already there are fine
+fn make_dir_ptr(path: *const libc::c_char) -> IOResult<()> {
+    if unsafe { libc::mkdir(path, 0o755) } != 0 && IOError::last_os_error().raw_os_error() != Some(libc::EEXIST) {
+        check(-1, "making a directory")?;
+    }
+    Ok(())
+}
+
+fn write_file(path: &str, contents: &[u8]) -> IOResult<()> {
+    let fd = check(unsafe { libc::open(c(path), libc::O_WRONLY | libc::O_CLOEXEC) }, "opening a namespace map")?;
+    let written = unsafe { libc::write(fd, contents.as_ptr() as *const libc::c_void, contents.len()) };
+    unsafe { libc::close(fd) };
+    check(written as i64, "writing a namespace map").map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs `program` in a jail that can see /usr and the libraries, and
+    // hands back whether it worked and what it printed
+    fn run_jailed(program: &str, args: &[&str]) -> (bool, String) {
+        let readonly : Vec<PathBuf> = ["/usr", "/bin", "/lib", "/lib64"].iter().map(PathBuf::from).collect();
+        let jail = Jail::new(&readonly, Path::new("/usr"), "1m").unwrap();
+        let mut cmd = StdCommand::new(program);
+        cmd.args(args);
+        jail.confine(&mut cmd);
+        let output = cmd.output().unwrap();
+        let mut said = String::from_utf8_lossy(&output.stdout).into_owned();
+        said.push_str(&String::from_utf8_lossy(&output.stderr));
+        (output.status.success(), said)
+    }
+
+    #[test]
+    fn runs_programs() {
+        let (ok, said) = run_jailed("/usr/bin/id", &["-u"]);
+        assert!(ok, "{}", said);
+        assert_eq!(said.trim(), JAIL_ID.to_string());
+    }
+
+    // Making a namespace is fine outside, it's the filter that stops it
+    #[test]
+    fn seccomp_denies_unshare() {
+        let (ok, said) = run_jailed("/usr/bin/unshare", &["--user", "/usr/bin/true"]);
+        assert!(!ok);
+        assert!(said.contains("Operation not permitted"), "{}", said);
+    }
+
+    // Only the loopback interface, and that isn't up
+    #[test]
+    fn no_network() {
+        let (ok, said) = run_jailed("/usr/bin/cat", &["/proc/net/dev"]);
+        assert!(ok, "{}", said);
+        let interfaces : Vec<&str> = said.lines()
+            .skip(2)
+            .filter_map(|line| line.split(':').next())
+            .map(|name| name.trim())
+            .collect();
+        assert_eq!(interfaces, vec!["lo"]);
+
+        let (ok, said) = run_jailed("/usr/bin/cat", &["/proc/net/route"]);
+        assert!(ok, "{}", said);
+        // Nothing past the header, if there even is one
+        assert_eq!(said.lines().skip(1).count(), 0, "{}", said);
+    }
+}
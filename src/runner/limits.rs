@@ -0,0 +1,234 @@
+// Caps on what an AI process can use, so one that runs away can't take the
+// host down with it. All but output are rlimits set on the AI itself, inside
+// the jail; output is counted by the server as it reads it.
+//
+// Going over CPU time or file size gets a signal from the kernel, and the
+// server counts output itself. Running out of memory, processes or files
+// just makes calls fail, so runners report it by exiting with the errno they
+// got: ENOMEM, EAGAIN from fork, or EMFILE. An AI could fake one of those,
+// but all it gets for it is forfeiting its own game. Anything else is a
+// crash.
+//
+// Processes are counted per user namespace, so in the jail the limit only
+// covers the AI and its own children. The kernel doesn't hold root to it,
+// so it does nothing when the server runs as root
+use std::fmt;
+use std::io::{Error as IOError, Result as IOResult};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command as StdCommand, ExitStatus};
+use tokio::time::{delay_for, Duration};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Limit {
+    Memory,
+    CpuTime,
+    Processes,
+    OpenFiles,
+    // Bytes written to stdout, stderr and files
+    Output,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Limits {
+    // Address space, which is what python runs out of first
+    pub memory_bytes: u64,
+    pub cpu_seconds: u64,
+    pub processes: u64,
+    pub open_files: u64,
+    pub output_bytes: u64,
+}
+
+// An AI went over one of its limits. Ends the game as a forfeit
+#[derive(Debug)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub value: u64,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            Limit::Memory => write!(f, "AI went over its memory limit of {} bytes", self.value),
+            Limit::CpuTime => write!(f, "AI went over its CPU time limit of {}s", self.value),
+            Limit::Processes => write!(f, "AI went over its limit of {} processes", self.value),
+            Limit::OpenFiles => write!(f, "AI went over its limit of {} open files", self.value),
+            Limit::Output => write!(f, "AI went over its output limit of {} bytes", self.value),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for IOError {
+    fn from(exceeded: LimitExceeded) -> Self {
+        IOError::other(exceeded)
+    }
+}
+
+// setrlimit takes its own type for the resource in glibc and uClibc, and a
+// plain int in musl
+#[cfg(any(target_env = "gnu", target_env = "uclibc"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(any(target_env = "gnu", target_env = "uclibc")))]
+type Resource = libc::c_int;
+
+impl Limits {
+    pub fn exceeded(&self, limit: Limit) -> LimitExceeded {
+        let value = match limit {
+            Limit::Memory => self.memory_bytes,
+            Limit::CpuTime => self.cpu_seconds,
+            Limit::Processes => self.processes,
+            Limit::OpenFiles => self.open_files,
+            Limit::Output => self.output_bytes,
+        };
+        LimitExceeded {limit, value}
+    }
+
+    // Makes `cmd` start with these limits. Goes after Jail::confine, so the
+    // limits land on the AI and not on the jail setting itself up
+    pub fn apply(self, cmd: &mut StdCommand) {
+        unsafe {
+            cmd.pre_exec(move || self.set());
+        }
+    }
+
+    // Runs in the child, between fork and exec
+    fn set(&self) -> IOResult<()> {
+        set_limit(libc::RLIMIT_AS, self.memory_bytes, self.memory_bytes)?;
+        // SIGXCPU at the soft limit, which kills python. SIGKILL a second
+        // later if something catches it
+        set_limit(libc::RLIMIT_CPU, self.cpu_seconds, self.cpu_seconds + 1)?;
+        set_limit(libc::RLIMIT_NPROC, self.processes, self.processes)?;
+        set_limit(libc::RLIMIT_NOFILE, self.open_files, self.open_files)?;
+        set_limit(libc::RLIMIT_FSIZE, self.output_bytes, self.output_bytes)?;
+        set_limit(libc::RLIMIT_CORE, 0, 0)
+    }
+
+    // Which limit, if any, an AI that exited with `status` after using `cpu`
+    // and the server reading `output` bytes from it ran into. Pass no status
+    // for AIs these limits weren't set on, since their signals and exit codes
+    // could mean anything
+    pub fn violation(&self, status: Option<ExitStatus>, cpu: Option<Duration>, output: u64) -> Option<LimitExceeded> {
+        if output > self.output_bytes {
+            return Some(self.exceeded(Limit::Output));
+        }
+        let status = status?;
+        let limit = match (status.signal(), status.code()) {
+            (Some(libc::SIGXCPU), _) => Limit::CpuTime,
+            // The hard CPU limit, if it caught SIGXCPU. Otherwise it was
+            // killed for something else, like running the jail out of memory
+            (Some(libc::SIGKILL), _) if cpu? >= Duration::from_secs(self.cpu_seconds) => Limit::CpuTime,
+            (Some(libc::SIGXFSZ), _) => Limit::Output,
+            (_, Some(libc::ENOMEM)) => Limit::Memory,
+            (_, Some(libc::EAGAIN)) => Limit::Processes,
+            (_, Some(libc::EMFILE)) => Limit::OpenFiles,
+            _ => return None,
+        };
+        Some(self.exceeded(limit))
+    }
+}
+
+// How much CPU time `pid` used, once it has exited. It has to be asked
+// before it's reaped, after which the kernel forgets. None if it's gone
+// already
+pub async fn cpu_time_at_exit(pid: u32) -> Option<Duration> {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    loop {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // The name goes in brackets and can have anything in it, so the
+        // fields are counted from after it: state, then utime and stime as
+        // the 12th and 13th
+        let fields : Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        if fields.first() == Some(&"Z") {
+            let utime : u64 = fields.get(11)?.parse().ok()?;
+            let stime : u64 = fields.get(12)?.parse().ok()?;
+            return Some(Duration::from_millis((utime + stime) * 1000 / ticks_per_second.max(1) as u64));
+        }
+        delay_for(Duration::from_millis(10)).await;
+    }
+}
+
+fn set_limit(resource: Resource, soft: u64, hard: u64) -> IOResult<()> {
+    let limit = libc::rlimit {rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t};
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(IOError::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS : Limits = Limits {
+        memory_bytes: 1 << 20,
+        cpu_seconds: 2,
+        processes: 4,
+        open_files: 8,
+        output_bytes: 1 << 10,
+    };
+
+    fn exited(code: i32) -> Option<ExitStatus> {
+        Some(ExitStatus::from_raw(code << 8))
+    }
+
+    fn killed(signal: i32) -> Option<ExitStatus> {
+        Some(ExitStatus::from_raw(signal))
+    }
+
+    fn limit(status: Option<ExitStatus>, cpu: Option<Duration>) -> Option<Limit> {
+        LIMITS.violation(status, cpu, 0).map(|exceeded| exceeded.limit)
+    }
+
+    #[test]
+    fn memory() {
+        assert_eq!(limit(exited(libc::ENOMEM), None), Some(Limit::Memory));
+        assert_eq!(LIMITS.exceeded(Limit::Memory).to_string(), "AI went over its memory limit of 1048576 bytes");
+    }
+
+    #[test]
+    fn cpu_time() {
+        assert_eq!(limit(killed(libc::SIGXCPU), None), Some(Limit::CpuTime));
+        assert_eq!(limit(killed(libc::SIGKILL), Some(Duration::from_secs(2))), Some(Limit::CpuTime));
+        // Killed well short of the limit, or without knowing, is a crash
+        assert_eq!(limit(killed(libc::SIGKILL), Some(Duration::from_millis(300))), None);
+        assert_eq!(limit(killed(libc::SIGKILL), None), None);
+    }
+
+    #[test]
+    fn processes() {
+        assert_eq!(limit(exited(libc::EAGAIN), None), Some(Limit::Processes));
+        assert_eq!(LIMITS.exceeded(Limit::Processes).to_string(), "AI went over its limit of 4 processes");
+    }
+
+    #[test]
+    fn open_files() {
+        assert_eq!(limit(exited(libc::EMFILE), None), Some(Limit::OpenFiles));
+        assert_eq!(LIMITS.exceeded(Limit::OpenFiles).to_string(), "AI went over its limit of 8 open files");
+    }
+
+    #[test]
+    fn output() {
+        assert_eq!(limit(killed(libc::SIGXFSZ), None), Some(Limit::Output));
+        assert_eq!(LIMITS.violation(exited(0), None, 1025).unwrap().limit, Limit::Output);
+        assert!(LIMITS.violation(exited(0), None, 1024).is_none());
+    }
+
+    #[test]
+    fn crashes_and_the_unjailed() {
+        assert_eq!(limit(exited(1), None), None);
+        assert_eq!(limit(killed(libc::SIGSEGV), None), None);
+        // Without a status, only the output the server counted
+        assert_eq!(limit(None, Some(Duration::from_secs(5))), None);
+    }
+
+    #[tokio::test]
+    async fn measures_cpu_time() {
+        let mut child = StdCommand::new("sh").arg("-c").arg("i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done").spawn().unwrap();
+        let cpu = cpu_time_at_exit(child.id()).await.unwrap();
+        child.wait().unwrap();
+        assert!(cpu > Duration::from_millis(50), "only {:?}", cpu);
+        // And nothing once it's reaped
+        assert!(cpu_time_at_exit(child.id()).await.is_none());
+    }
+}
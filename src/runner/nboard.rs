@@ -0,0 +1,331 @@
+// Engines speaking the NBoard protocol over stdin and stdout, like Edax and
+// NTest. The server plays the part of the NBoard GUI:
+//   -> nboard 2
+//   -> set depth 12
+//   -> set game (;GM[Othello]PB[]PW[]TY[8]BO[8 ---...--- *];)
+//   -> move F5
+//   -> ping 3
+//   -> go
+//   <- pong 3
+//   <- === D6/-1.50/0.8
+// Engines get every move as it's played, their own included, and the whole
+// game again whenever they fall out of step. Anything else they print, like
+// status lines, is ignored
+use futures::future::BoxFuture;
+use log::*;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::{timeout, timeout_at, Instant};
+
+use crate::othello::{BoardStruct, Player};
+use crate::othello::bitboard::Geometry;
+use crate::othello::ggf::{to_ggf, GgfGame};
+use crate::othello::moves::{Loc, MoveRecord};
+use crate::othello::notation::{from_algebraic, to_algebraic};
+use crate::othello::record::GameRecord;
+use super::backend::{self, Backend};
+use super::{settings, Runner};
+use super::limits::Limits;
+
+pub struct Nboard {
+    engine: String,
+    // Until it's started
+    command: Option<Command>,
+    limits: Limits,
+    runner: Option<Runner>,
+    // The game as the engine has it, once it's been sent one
+    game: Option<GameRecord>,
+    depth: Option<u32>,
+    // Pongs for older pings are stale
+    pings: u32,
+}
+
+impl Nboard {
+    pub fn new(engine: &str, command: Command, limits: Limits) -> Self {
+        Nboard {
+            engine: engine.to_string(),
+            command: Some(command),
+            limits,
+            runner: None,
+            game: None,
+            depth: None,
+            pings: 0,
+        }
+    }
+}
+
+async fn send(runner: &mut Runner, line: &str) -> IOResult<()> {
+    debug!("Sending \"{}\" to NBoard engine {}", line, runner.ai_name);
+    runner.stdin.write_all(format!("{}\n", line).as_bytes()).await
+}
+
+// The engine's next line, or why there isn't one
+async fn next_line(runner: &mut Runner) -> IOResult<String> {
+    match super::read_reply(runner).await? {
+        Some(line) => Ok(line),
+        None => Err(super::died(runner).await),
+    }
+}
+
+// Reads up to the pong for ping `n`, which means the engine has caught up
+// with everything sent before it
+async fn await_pong(runner: &mut Runner, n: u32) -> IOResult<()> {
+    let pong = format!("pong {}", n);
+    loop {
+        let line = next_line(runner).await?;
+        if line.trim() == pong {
+            return Ok(());
+        }
+        debug!("NBoard engine {} said \"{}\"", runner.ai_name, line);
+    }
+}
+
+// Moves look like "F5", "F5/1.23/4.5" with an evaluation and time, or "PA"
+async fn await_move(runner: &mut Runner, geometry: &Geometry) -> IOResult<Loc> {
+    loop {
+        let line = next_line(runner).await?;
+        let reply = match line.trim().strip_prefix("===") {
+            Some(reply) => reply.trim(),
+            None => {
+                debug!("NBoard engine {} said \"{}\"", runner.ai_name, line);
+                continue;
+            },
+        };
+        let square = reply.split('/').next().unwrap_or("");
+        return from_algebraic(geometry, square).ok_or_else(|| IOError::new(
+            IOErrorKind::InvalidData,
+            format!("engine replied {:?}, which is not a square", line),
+        ));
+    }
+}
+
+fn ggf_move(geometry: &Geometry, square: Option<Loc>) -> String {
+    match square.and_then(|sq| to_algebraic(geometry, sq)) {
+        Some(name) => name.to_uppercase(),
+        None => "PA".to_string(),
+    }
+}
+
+// The deepest search that fits in `seconds`
+fn depth_for(seconds: f32) -> u32 {
+    settings::NBOARD_DEPTHS
+        .iter()
+        .take_while(|(time, _)| *time <= seconds)
+        .last()
+        .map_or(1, |(_, depth)| *depth)
+}
+
+impl Nboard {
+    fn runner(&mut self) -> IOResult<&mut Runner> {
+        self.runner.as_mut().ok_or_else(|| IOError::new(IOErrorKind::NotConnected, "NBoard engine was used before being started!"))
+    }
+
+    // Plays `square` in the engine's game and tells it, or drops the game if
+    // it doesn't fit, so the next move starts over
+    async fn play(&mut self, player: Player, square: Option<Loc>) -> IOResult<()> {
+        let played = match &mut self.game {
+            Some(game) if game.tomove() == player => match square {
+                Some(sq) => game.play(sq).is_ok(),
+                None => game.pass().is_ok(),
+            },
+            _ => false,
+        };
+        match &self.game {
+            Some(game) if played => {
+                let line = format!("move {}", ggf_move(game.board().geometry(), square));
+                send(self.runner()?, &line).await
+            },
+            _ => {
+                self.game = None;
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Backend for Nboard {
+    fn start(&mut self) -> BoxFuture<'_, IOResult<()>> {
+        Box::pin(async move {
+            let command = self.command.take().ok_or_else(|| IOError::new(IOErrorKind::AlreadyExists, "NBoard engine was already started!"))?;
+            let runner = self.runner.get_or_insert(super::make_runner(&self.engine, command, self.limits)?);
+            send(runner, "nboard 2").await?;
+            send(runner, "ping 0").await?;
+            match timeout(settings::NBOARD_START_TIME, await_pong(runner, 0)).await {
+                Ok(result) => result,
+                Err(_) => Err(IOError::new(IOErrorKind::TimedOut, "NBoard engine took too long to start!")),
+            }
+        })
+    }
+
+    fn get_move<'a>(&'a mut self, board: &'a BoardStruct, player: Player, deadline: Instant) -> BoxFuture<'a, IOResult<usize>> {
+        Box::pin(async move {
+            let depth = depth_for(backend::seconds_left(deadline));
+            if self.depth != Some(depth) {
+                send(self.runner()?, &format!("set depth {}", depth)).await?;
+                self.depth = Some(depth);
+            }
+
+            let in_step = match &self.game {
+                Some(game) => game.board() == board && game.tomove() == player,
+                None => false,
+            };
+            if !in_step {
+                let game = GameRecord::from_position(*board, player);
+                let line = format!("set game {}", to_ggf(&GgfGame::new("", "", game.clone())));
+                send(self.runner()?, &line).await?;
+                self.game = Some(game);
+            }
+
+            self.pings += 1;
+            let ping = self.pings;
+            let runner = self.runner()?;
+            send(runner, &format!("ping {}", ping)).await?;
+            send(runner, "go").await?;
+            let reply = async {
+                await_pong(runner, ping).await?;
+                await_move(runner, board.geometry()).await
+            };
+            let square = match timeout_at(deadline + backend::GRACE, reply).await {
+                Ok(square) => square?,
+                Err(_) => return Err(IOError::new(IOErrorKind::TimedOut, "NBoard engine ran out of time!")),
+            };
+
+            // The engine doesn't play its own move until it's told to
+            self.play(player, Some(square)).await?;
+            Ok(square)
+        })
+    }
+
+    fn notify<'a>(&'a mut self, played: &'a MoveRecord) -> BoxFuture<'a, IOResult<()>> {
+        Box::pin(self.play(played.player, played.square))
+    }
+
+    fn stop(self: Box<Self>) -> BoxFuture<'static, IOResult<String>> {
+        Box::pin(async move {
+            match self.runner {
+                Some(mut runner) => {
+                    send(&mut runner, "quit").await?;
+                    super::wait_and_get_error(runner).await
+                },
+                None => Ok(String::new()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::process::Stdio;
+    use tokio::time::Duration;
+    use crate::othello::variant::Variant;
+
+    // Writes down everything it's sent, and answers each go with the next of
+    // its arguments, then stops answering
+    const FAKE_ENGINE : &str = r#"#!/bin/sh
+log="$1"
+shift
+while read -r line; do
+    echo "$line" >> "$log"
+    case "$line" in
+        ping*) echo "pong ${line#ping }" ;;
+        go)
+            echo "status thinking"
+            if [ $# -gt 0 ]; then
+                echo "=== $1/0.00/0.1"
+                shift
+            fi ;;
+        quit) exit 0 ;;
+    esac
+done
+"#;
+
+    // The fake engine, answering with `moves`, and the directory it's in,
+    // where its log goes
+    fn fake_engine(name: &str, moves: &[&str]) -> (Nboard, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("nboard-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("engine");
+        fs::write(&program, FAKE_ENGINE).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let log = dir.join("log");
+
+        let mut cmd = Command::new(&program);
+        cmd
+            .arg(&log)
+            .args(moves)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        (Nboard::new(name, cmd, settings::RUNNER_LIMITS), dir)
+    }
+
+    // Well inside the time for depth 12
+    fn deadline() -> Instant {
+        Instant::now() + Duration::from_secs(3)
+    }
+
+    #[tokio::test]
+    async fn plays_along() {
+        let (engine, dir) = fake_engine("plays-along", &["D3", "E6"]);
+        let mut engine: Box<dyn Backend> = Box::new(engine);
+        engine.start().await.unwrap();
+
+        let mut game = GameRecord::from_position(BoardStruct::new(), Player::Black);
+        assert_eq!(engine.get_move(game.board(), Player::Black, deadline()).await.unwrap(), 34);
+        game.play(34).unwrap();
+        let played = game.play(33).unwrap().clone();
+        engine.notify(&played).await.unwrap();
+        // Still in step, so there's no new game
+        assert_eq!(engine.get_move(game.board(), Player::Black, deadline()).await.unwrap(), 65);
+        engine.stop().await.unwrap();
+
+        let sent = fs::read_to_string(dir.join("log")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let sent : Vec<&str> = sent.lines().collect();
+        assert_eq!(sent, [
+            "nboard 2",
+            "ping 0",
+            "set depth 12",
+            "set game (;GM[Othello]PB[]PW[]TY[8]BO[8 ---------------------------O*------*O--------------------------- *];)",
+            "ping 1",
+            "go",
+            "move D3",
+            "move C3",
+            "ping 2",
+            "go",
+            "move E6",
+            "quit",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let (engine, dir) = fake_engine("times-out", &[]);
+        let mut engine: Box<dyn Backend> = Box::new(engine);
+        engine.start().await.unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let why = engine.get_move(&BoardStruct::new(), Player::Black, deadline).await.unwrap_err();
+        assert_eq!(why.kind(), IOErrorKind::TimedOut);
+        engine.stop().await.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_standard_board() {
+        let name = "nboard:edax".to_string();
+        let small = Variant {size: 6, anti: false};
+        let why = super::super::make_backend(&name, &small).err().unwrap();
+        assert_eq!(why.kind(), IOErrorKind::InvalidInput);
+        assert_eq!(why.to_string(), "NBoard engines only play on 8x8 boards, not 6x6");
+        let anti = Variant {size: 8, anti: true};
+        assert_eq!(super::super::make_backend(&name, &anti).err().unwrap().kind(), IOErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,243 @@
+// Bots hosted elsewhere, played over a websocket. The server connects to the
+// bot, then asks it for moves and tells it what the other side played:
+//   -> {"type": "move_request", "board": "???...", "tomove": "@", "squares": [34, ...], "timelimit": 4.98}
+//   <- {"type": "movereply", "square": 34}
+//   -> {"type": "move_played", "player": "o", "square": 43}
+// Squares are TJHSST numbers, though replies can use algebraic names too,
+// and passes are played on a null square
+use futures::future::BoxFuture;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use tokio::time::{timeout, timeout_at, Duration, Instant};
+use tokio_tungstenite::connect_async;
+use tungstenite::{
+    Message as WSMessage,
+    Result as WSResult,
+    error::Error as WSError,
+};
+
+use crate::othello::{BoardStruct, Player};
+use crate::othello::moves::{legal_moves, Loc, MoveRecord};
+use crate::othello::notation::from_algebraic;
+use super::backend::{self, Backend, Resigned};
+use super::settings;
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum BotMessage<'a> {
+    #[serde(rename = "move_request")]
+    MoveRequest {board: &'a BoardStruct, tomove: Player, squares: Vec<Loc>, timelimit: f32},
+    #[serde(rename = "move_played")]
+    MovePlayed {player: Player, square: Option<Loc>},
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum BotReply {
+    #[serde(rename = "movereply")]
+    MoveReply {square: BotSquare},
+    #[serde(rename = "resign")]
+    Resign {},
+    #[serde(rename = "disconnect")]
+    Disconnect {},
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BotSquare {
+    Number(Loc),
+    Name(String),
+}
+
+// Whatever connect_async hands back, which depends on the TLS features
+trait Socket: Sink<WSMessage, Error=WSError> + Stream<Item=WSResult<WSMessage>> + Send + Unpin {}
+
+impl<T: Sink<WSMessage, Error=WSError> + Stream<Item=WSResult<WSMessage>> + Send + Unpin> Socket for T {}
+
+pub struct Remote {
+    url: String,
+    connect_time: Duration,
+    // Once it's started
+    socket: Option<Box<dyn Socket>>,
+}
+
+impl Remote {
+    pub fn new(url: &str) -> Self {
+        Remote {url: url.to_string(), connect_time: settings::REMOTE_CONNECT_TIME, socket: None}
+    }
+
+    fn socket(&mut self) -> IOResult<&mut Box<dyn Socket>> {
+        self.socket.as_mut().ok_or_else(|| IOError::new(IOErrorKind::NotConnected, "Remote bot was used before being started!"))
+    }
+}
+
+// Problems talking to the bot are the bot's problem, whatever they are
+fn hung_up(why: WSError) -> IOError {
+    match why {
+        WSError::Io(io) => io,
+        other => IOError::new(IOErrorKind::ConnectionAborted, other.to_string()),
+    }
+}
+
+async fn send(socket: &mut Box<dyn Socket>, msg: &BotMessage<'_>) -> IOResult<()> {
+    let text = serde_json::to_string(msg)?;
+    socket.send(WSMessage::Text(text)).await.map_err(hung_up)
+}
+
+impl Backend for Remote {
+    fn start(&mut self) -> BoxFuture<'_, IOResult<()>> {
+        Box::pin(async move {
+            let (socket, _) = match timeout(self.connect_time, connect_async(self.url.as_str())).await {
+                Ok(connected) => connected.map_err(hung_up)?,
+                Err(_) => return Err(IOError::new(IOErrorKind::TimedOut, "Remote bot took too long to connect!")),
+            };
+            self.socket = Some(Box::new(socket));
+            Ok(())
+        })
+    }
+
+    fn get_move<'a>(&'a mut self, board: &'a BoardStruct, player: Player, deadline: Instant) -> BoxFuture<'a, IOResult<usize>> {
+        Box::pin(async move {
+            let socket = self.socket()?;
+            let request = BotMessage::MoveRequest {
+                board,
+                tomove: player,
+                squares: legal_moves(&player, board),
+                timelimit: backend::seconds_left(deadline),
+            };
+            send(socket, &request).await?;
+
+            loop {
+                let reply = match timeout_at(deadline + backend::GRACE, socket.next()).await {
+                    Ok(Some(reply)) => reply.map_err(hung_up)?,
+                    Ok(None) => return Err(IOError::new(IOErrorKind::BrokenPipe, "Remote bot hung up!")),
+                    Err(_) => return Err(IOError::new(IOErrorKind::TimedOut, "Remote bot ran out of time!")),
+                };
+                let text = match reply {
+                    WSMessage::Text(text) => text,
+                    WSMessage::Close(_) => return Err(IOError::new(IOErrorKind::BrokenPipe, "Remote bot hung up!")),
+                    // Pings and the like
+                    _ => continue,
+                };
+                return match serde_json::from_str::<BotReply>(&text) {
+                    Ok(BotReply::MoveReply {square: BotSquare::Number(square)}) => Ok(square),
+                    Ok(BotReply::MoveReply {square: BotSquare::Name(name)}) => {
+                        from_algebraic(board.geometry(), &name)
+                            .ok_or_else(|| IOError::new(IOErrorKind::InvalidData, format!("\"{}\" is not a square", name)))
+                    },
+                    Ok(BotReply::Resign {}) | Ok(BotReply::Disconnect {}) => Err(IOError::other(Resigned(player))),
+                    Err(why) => Err(IOError::new(
+                        IOErrorKind::InvalidData,
+                        format!("remote bot replied {:?}, which is not a move: {}", text, why),
+                    )),
+                };
+            }
+        })
+    }
+
+    fn notify<'a>(&'a mut self, played: &'a MoveRecord) -> BoxFuture<'a, IOResult<()>> {
+        Box::pin(async move {
+            let msg = BotMessage::MovePlayed {player: played.player, square: played.square};
+            send(self.socket()?, &msg).await
+        })
+    }
+
+    fn stop(self: Box<Self>) -> BoxFuture<'static, IOResult<String>> {
+        Box::pin(async move {
+            if let Some(mut socket) = self.socket {
+                // It's going away either way
+                let _ = socket.close().await;
+            }
+            Ok(String::new())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio::time::Duration;
+    use tokio_tungstenite::accept_async;
+
+    // What the bot on the other end heard, one message at a time
+    async fn heard<S: Stream<Item=WSResult<WSMessage>> + Unpin>(socket: &mut S) -> Option<Value> {
+        match socket.next().await?.ok()? {
+            WSMessage::Text(text) => Some(serde_json::from_str(&text).unwrap()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn plays_over_loopback() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bot = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+
+            let request = heard(&mut socket).await.unwrap();
+            assert_eq!(request["type"], "move_request");
+            assert_eq!(request["tomove"], "@");
+            assert_eq!(request["squares"], json!([34, 43, 56, 65]));
+            socket.send(WSMessage::Text(json!({"type": "movereply", "square": "d3"}).to_string())).await.unwrap();
+
+            let played = heard(&mut socket).await.unwrap();
+            assert_eq!(played, json!({"type": "move_played", "player": "o", "square": 33}));
+            assert_eq!(heard(&mut socket).await.unwrap()["type"], "move_request");
+            socket.send(WSMessage::Text(json!({"type": "movereply", "square": 35}).to_string())).await.unwrap();
+
+            // Then the server hangs up
+            heard(&mut socket).await
+        });
+
+        let mut remote: Box<dyn Backend> = Box::new(Remote::new(&format!("ws://{}", addr)));
+        remote.start().await.unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let board = BoardStruct::new();
+        assert_eq!(remote.get_move(&board, Player::Black, deadline).await.unwrap(), 34);
+        let played = MoveRecord {player: Player::White, square: Some(33), flipped: Vec::new()};
+        remote.notify(&played).await.unwrap();
+        assert_eq!(remote.get_move(&board, Player::Black, deadline).await.unwrap(), 35);
+        remote.stop().await.unwrap();
+        assert_eq!(bot.await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn resigning_is_marked() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bot = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            heard(&mut socket).await.unwrap();
+            socket.send(WSMessage::Text(json!({"type": "resign"}).to_string())).await.unwrap();
+            heard(&mut socket).await
+        });
+
+        let mut remote: Box<dyn Backend> = Box::new(Remote::new(&format!("ws://{}", addr)));
+        remote.start().await.unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let why = remote.get_move(&BoardStruct::new(), Player::White, deadline).await.unwrap_err();
+        let resigned = why.get_ref().and_then(|inner| inner.downcast_ref::<Resigned>());
+        assert!(matches!(resigned, Some(Resigned(Player::White))), "{:?}", why);
+        remote.stop().await.unwrap();
+        bot.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn connecting_times_out() {
+        // Takes the connection but never answers the handshake
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bot = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let mut remote = Remote::new(&format!("ws://{}", addr));
+        remote.connect_time = Duration::from_millis(200);
+        let why = remote.start().await.unwrap_err();
+        assert_eq!(why.kind(), IOErrorKind::TimedOut);
+        drop(bot.await.unwrap());
+    }
+}
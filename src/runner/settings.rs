@@ -1,33 +1,308 @@
 use tokio::process::Command;
-use std::process::Stdio;
+use std::process::{Command as StdCommand, Stdio};
 use std::ffi::OsStr;
 use std::fs::canonicalize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tokio::io::{Error as IOError, ErrorKind as IOErrorKind};
+use tokio::time::Duration;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+use super::jail::Jail;
+use super::limits::Limits;
 
 // TODO: read this in from a toml file/command line arg or something
 pub const OTHELLO_ROOT : &str = "../othello_tourney/";
 pub const RUN_AI_FILENAME : &str = "run_ai_jailed.py";
 pub const HUMAN_PLAYER : &str = "Yourself";
 
-pub fn build_unjailed_command<S: AsRef<OsStr>>(ai_name: S) -> Result<Command, tokio::io::Error> {
+// Random openings come from the list built into othello::xot, unless
+// XOT_ENV names a file of them, like the published XOT list
+pub const XOT_ENV : &str = "OTHELLO_XOT";
+// Names starting with this are built-in players, e.g. "builtin:alphabeta:6"
+pub const BUILTIN_PREFIX : &str = "builtin:";
+// Names starting with this are native programs in NATIVE_ROOT, e.g.
+// "native:edgar". They get the same requests on stdin as run_ai_jailed.py
+pub const NATIVE_PREFIX : &str = "native:";
+pub const NATIVE_ROOT : &str = "../othello_tourney/native/";
+// Names starting with this are bots hosted elsewhere, reached over a
+// websocket, e.g. "remote:club". Only the ones in REMOTE_BOTS_FILE, a JSON
+// object of names to URLs like {"club": "wss://bots.example.org/club"}, so
+// clients can't point the server at whatever address they like.
+// REMOTE_BOTS_ENV points somewhere else instead
+pub const REMOTE_PREFIX : &str = "remote:";
+pub const REMOTE_BOTS_FILE : &str = "../othello_tourney/remote_bots.json";
+pub const REMOTE_BOTS_ENV : &str = "OTHELLO_REMOTE_BOTS";
+// How long a remote bot gets to take the connection and answer the handshake
+pub const REMOTE_CONNECT_TIME : Duration = Duration::from_secs(10);
+// Names starting with this are engines speaking the NBoard protocol, e.g.
+// "nboard:edax". Only the ones in NBOARD_ENGINES_FILE, a JSON object of
+// names to programs under NBOARD_ROOT and their arguments, like
+// {"edax": {"program": "edax/lEdax-x64", "args": ["-nboard"]}}.
+// NBOARD_ENGINES_ENV points somewhere else instead. They run jailed in the
+// program's directory, where engines look for their evaluation data
+pub const NBOARD_PREFIX : &str = "nboard:";
+pub const NBOARD_ROOT : &str = "../othello_tourney/nboard/";
+pub const NBOARD_ENGINES_FILE : &str = "../othello_tourney/nboard/engines.json";
+pub const NBOARD_ENGINES_ENV : &str = "OTHELLO_NBOARD_ENGINES";
+// How long an NBoard engine gets to load before it has to answer a ping
+pub const NBOARD_START_TIME : Duration = Duration::from_secs(10);
+// NBoard engines search to a depth rather than watching a clock, so the time
+// limit picks one: the deepest whose time fits, by how long Edax takes on
+// one core in the midgame
+pub const NBOARD_DEPTHS : [(f32, u32); 7] = [
+    (0.0, 4),
+    (0.5, 8),
+    (1.0, 10),
+    (2.0, 12),
+    (5.0, 14),
+    (10.0, 16),
+    (30.0, 20),
+];
+
+// Host paths the jail can see, read-only, besides OTHELLO_ROOT. Python and
+// everything it loads has to be in here. Ones that don't exist are skipped
+pub const JAIL_READONLY_PATHS : [&str; 6] = ["/usr", "/bin", "/lib", "/lib64", "/etc/ld.so.cache", "/etc/alternatives"];
+// How big the jail's private /tmp can get
+pub const JAIL_TMP_SIZE : &str = "64m";
+// Setting this runs AIs without the jail. Only for development on machines
+// that can't make one
+pub const UNJAILED_ENV : &str = "OTHELLO_UNJAILED";
+// How long a runner gets to exit once it's been told to stop, before it's
+// killed
+pub const RUNNER_STOP_TIME : Duration = Duration::from_secs(2);
+// What each AI process gets to use, unless RUNNER_LIMITS_FILE says
+// otherwise. Only output is enforced on unjailed AIs, since process counts
+// there would include everything else the server's user is running
+pub const RUNNER_LIMITS : Limits = Limits {
+    memory_bytes: 1 << 30,
+    cpu_seconds: 300,
+    processes: 32,
+    open_files: 64,
+    output_bytes: 1 << 20,
+};
+// A JSON object of player names, as clients ask for them, to the limits that
+// are different for them, like {"native:edgar": {"cpu_seconds": 600}}. The
+// name "*" changes them for everyone, under any of their own.
+// RUNNER_LIMITS_ENV points somewhere else instead
+pub const RUNNER_LIMITS_FILE : &str = "../othello_tourney/limits.json";
+pub const RUNNER_LIMITS_ENV : &str = "OTHELLO_RUNNER_LIMITS";
+
+// One of the engines in NBOARD_ENGINES_FILE
+#[derive(Deserialize)]
+struct NboardEngine {
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+// One of the entries in RUNNER_LIMITS_FILE
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LimitChanges {
+    memory_bytes: Option<u64>,
+    cpu_seconds: Option<u64>,
+    processes: Option<u64>,
+    open_files: Option<u64>,
+    output_bytes: Option<u64>,
+}
+
+impl LimitChanges {
+    fn apply(&self, limits: Limits) -> Limits {
+        Limits {
+            memory_bytes: self.memory_bytes.unwrap_or(limits.memory_bytes),
+            cpu_seconds: self.cpu_seconds.unwrap_or(limits.cpu_seconds),
+            processes: self.processes.unwrap_or(limits.processes),
+            open_files: self.open_files.unwrap_or(limits.open_files),
+            output_bytes: self.output_bytes.unwrap_or(limits.output_bytes),
+        }
+    }
+}
+
+fn python_command<S: AsRef<OsStr>>(ai_name: S) -> Result<(StdCommand, PathBuf), IOError> {
     let canonical_root = canonicalize(OTHELLO_ROOT)?;
     let mut run_file = canonical_root.clone();
     run_file.push(RUN_AI_FILENAME);
 
-    let mut cmd = Command::new("python");
+    let mut cmd = StdCommand::new("python");
     cmd
         .arg("-u")
         .arg(run_file)
-        .arg(ai_name)
+        .arg(ai_name);
+
+    Ok((cmd, canonical_root))
+}
+
+// Makes `cmd` start in the jail, seeing `workdir` as well as the usual paths
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn confine(cmd: &mut StdCommand, workdir: &Path) -> Result<(), IOError> {
+    let readonly : Vec<PathBuf> = JAIL_READONLY_PATHS.iter().map(PathBuf::from).collect();
+    Jail::new(&readonly, workdir, JAIL_TMP_SIZE)?.confine(cmd);
+    Ok(())
+}
+
+// The jail is made of Linux namespaces, and its seccomp filter only knows
+// the syscall numbers of these two architectures
+#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn confine(_cmd: &mut StdCommand, _workdir: &Path) -> Result<(), IOError> {
+    Err(IOError::new(IOErrorKind::Unsupported, format!(
+        "the jail is unsupported here, it needs Linux on x86_64 or aarch64; set {} to run AIs without it",
+        UNJAILED_ENV,
+    )))
+}
+
+// Whether AIs run in the jail, with RUNNER_LIMITS on them
+pub fn jailed() -> bool {
+    std::env::var_os(UNJAILED_ENV).is_none()
+}
+
+// Hooks up `cmd`'s pipes and starts it in `workdir`, in the jail with
+// `limits` unless it's turned off
+fn finish_command(mut cmd: StdCommand, workdir: &Path, limits: Limits) -> Result<Command, IOError> {
+    cmd
         .stderr(Stdio::piped())
         .stdout(Stdio::piped())
         .stdin(Stdio::piped())
-        .current_dir(canonical_root)
-        .kill_on_drop(true);
+        .current_dir(workdir);
+    if jailed() {
+        confine(&mut cmd, workdir)?;
+        limits.apply(&mut cmd);
+    }
 
+    let mut cmd = Command::from(cmd);
+    cmd.kill_on_drop(true);
     Ok(cmd)
 }
 
-pub fn build_jailed_command<S: AsRef<OsStr>>(ai_name: S) -> Result<Command, tokio::io::Error> {
-    // TODO: actually implement this
-    build_unjailed_command(ai_name)
+pub fn build_jailed_command<S: AsRef<OsStr>>(ai_name: S, limits: Limits) -> Result<Command, IOError> {
+    let (cmd, canonical_root) = python_command(ai_name)?;
+    finish_command(cmd, &canonical_root, limits)
+}
+
+// `program` has to be right in NATIVE_ROOT, and it only sees that directory
+pub fn build_native_command(program: &str, limits: Limits) -> Result<Command, IOError> {
+    if program.is_empty() || program.starts_with('.') || program.contains('/') {
+        return Err(IOError::new(IOErrorKind::InvalidInput, format!("{:?} is not a native program name", program)));
+    }
+    let canonical_root = canonicalize(NATIVE_ROOT)?;
+    let path = canonical_root.join(program);
+    if !path.is_file() {
+        return Err(IOError::new(IOErrorKind::NotFound, format!("There is no native program called {}", program)));
+    }
+    finish_command(StdCommand::new(path), &canonical_root, limits)
+}
+
+pub fn build_nboard_command(engine: &str, limits: Limits) -> Result<Command, IOError> {
+    let NboardEngine {program, args} = match read_registry(NBOARD_ENGINES_ENV, NBOARD_ENGINES_FILE)?.remove(engine) {
+        Some(known) => known,
+        None => return Err(IOError::new(IOErrorKind::NotFound, format!("There is no NBoard engine called {}", engine))),
+    };
+    let canonical_root = canonicalize(NBOARD_ROOT)?;
+    let path = canonicalize(canonical_root.join(&program))?;
+    if !path.starts_with(&canonical_root) || !path.is_file() {
+        return Err(IOError::new(IOErrorKind::InvalidInput, format!("{} is not a program in {}", program, NBOARD_ROOT)));
+    }
+    // Always there, since the path is a file in canonical_root
+    let workdir = path.parent().unwrap().to_path_buf();
+
+    let mut cmd = StdCommand::new(&path);
+    cmd.args(&args);
+    finish_command(cmd, &workdir, limits)
+}
+
+// Starts python in the jail once, so a kernel that can't make one is found
+// at startup rather than on the first game
+pub fn check_jail() -> Result<(), IOError> {
+    let canonical_root = canonicalize(OTHELLO_ROOT)?;
+    let mut cmd = StdCommand::new("python");
+    cmd
+        .arg("-c")
+        .arg("pass")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .current_dir(&canonical_root);
+    confine(&mut cmd, &canonical_root)?;
+    RUNNER_LIMITS.apply(&mut cmd);
+
+    let status = cmd.status().map_err(|why| IOError::new(
+        why.kind(),
+        format!(
+            "could not start a jailed runner ({}). The jail needs unprivileged user namespaces \
+            (check /proc/sys/user/max_user_namespaces and kernel.unprivileged_userns_clone); \
+            set {} to run AIs without it",
+            why, UNJAILED_ENV,
+        ),
+    ))?;
+    if !status.success() {
+        return Err(IOError::other(format!("python exited with {} inside the jail", status)));
+    }
+    Ok(())
+}
+
+pub fn xot_file() -> Option<PathBuf> {
+    std::env::var_os(XOT_ENV).map(PathBuf::from)
+}
+
+// One of the JSON registries above, from the file in `env` if it's set or
+// else `default`. With neither file there, nothing is registered
+fn read_registry<T: DeserializeOwned>(env: &str, default: &str) -> Result<HashMap<String, T>, IOError> {
+    let (path, required) = match std::env::var_os(env) {
+        Some(path) => (PathBuf::from(path), true),
+        None => (PathBuf::from(default), false),
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(why) if why.kind() == IOErrorKind::NotFound && !required => return Ok(HashMap::new()),
+        Err(why) => return Err(IOError::new(why.kind(), format!("could not read {}: {}", path.display(), why))),
+    };
+    serde_json::from_str(&text).map_err(|why| IOError::new(
+        IOErrorKind::InvalidData,
+        format!("{} is not a valid registry: {}", path.display(), why),
+    ))
+}
+
+// The limits on the player called `name`: RUNNER_LIMITS, with the changes
+// for everyone and then its own from RUNNER_LIMITS_FILE
+pub fn runner_limits(name: &str) -> Result<Limits, IOError> {
+    let changes = read_registry(RUNNER_LIMITS_ENV, RUNNER_LIMITS_FILE)?;
+    Ok(limits_for(&changes, name))
+}
+
+fn limits_for(changes: &HashMap<String, LimitChanges>, name: &str) -> Limits {
+    ["*", name].iter()
+        .filter_map(|key| changes.get(*key))
+        .fold(RUNNER_LIMITS, |limits, changes| changes.apply(limits))
+}
+
+pub fn remote_bot_url(bot: &str) -> Result<String, IOError> {
+    match read_registry::<String>(REMOTE_BOTS_ENV, REMOTE_BOTS_FILE)?.remove(bot) {
+        Some(url) => Ok(url),
+        None => Err(IOError::new(IOErrorKind::NotFound, format!("There is no remote bot called {}", bot))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runners_get_their_own_limits() {
+        let changes = serde_json::from_str(r#"{
+            "*": {"cpu_seconds": 60, "processes": 8},
+            "native:edgar": {"cpu_seconds": 600, "memory_bytes": 4096}
+        }"#).unwrap();
+
+        let everyone = limits_for(&changes, "some_ai");
+        assert_eq!(everyone, Limits {cpu_seconds: 60, processes: 8, ..RUNNER_LIMITS});
+        let edgar = limits_for(&changes, "native:edgar");
+        assert_eq!(edgar, Limits {cpu_seconds: 600, memory_bytes: 4096, processes: 8, ..RUNNER_LIMITS});
+        assert_eq!(limits_for(&HashMap::new(), "native:edgar"), RUNNER_LIMITS);
+    }
+
+    #[test]
+    fn unknown_limits_are_errors() {
+        assert!(serde_json::from_str::<HashMap<String, LimitChanges>>(r#"{"*": {"cpu": 60}}"#).is_err());
+    }
 }
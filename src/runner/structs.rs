@@ -1,22 +1,33 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicU64;
 use tokio::process::{
     Child,
     ChildStdin,
     ChildStdout,
-    ChildStderr,
-};
-use tokio::io::{
-    BufReader,
-    Lines,
 };
+use tokio::io::BufReader;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use super::limits::Limits;
 
 pub type RunnerStdin = ChildStdin;
-pub type RunnerStdout = Lines<BufReader<ChildStdout>>;
-pub type RunnerStderr = BufReader<ChildStderr>;
+pub type RunnerStdout = BufReader<ChildStdout>;
 
 pub struct Runner {
     pub child: Child,
     pub stdin: RunnerStdin,
     pub stdout: RunnerStdout,
-    pub stderr: RunnerStderr,
     pub ai_name: String,
+    pub limits: Limits,
+    // Bytes read from stdout and stderr so far, against the output limit
+    pub output_read: Arc<AtomicU64>,
+    // Stderr is read as it comes in by its own task, so the runner can't
+    // get stuck writing to a full pipe. This is what it said, up to the
+    // output limit
+    pub stderr: Arc<Mutex<String>>,
+    pub stderr_task: Option<JoinHandle<()>>,
+    // Told when stderr goes over the output limit, after which it isn't
+    // read any more
+    pub over_limit: Arc<Notify>,
 }
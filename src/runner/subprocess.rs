@@ -0,0 +1,62 @@
+// AIs run as programs talking the JailedRunner protocol over stdin and
+// stdout: the python ones through run_ai_jailed.py, and native executables
+use futures::future::BoxFuture;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
+use tokio::process::Command;
+use tokio::time::Instant;
+
+use crate::othello::{BoardStruct, Player};
+use super::backend::{self, Backend};
+use super::Runner;
+use super::limits::Limits;
+
+pub struct Subprocess {
+    ai_name: String,
+    // Until it's started
+    command: Option<Command>,
+    limits: Limits,
+    runner: Option<Runner>,
+}
+
+impl Subprocess {
+    pub fn new(ai_name: &str, command: Command, limits: Limits) -> Self {
+        Subprocess {
+            ai_name: ai_name.to_string(),
+            command: Some(command),
+            limits,
+            runner: None,
+        }
+    }
+}
+
+impl Backend for Subprocess {
+    fn start(&mut self) -> BoxFuture<'_, IOResult<()>> {
+        Box::pin(async move {
+            match self.command.take() {
+                Some(command) => {
+                    self.runner = Some(super::make_runner(&self.ai_name, command, self.limits)?);
+                    Ok(())
+                },
+                None => Err(IOError::new(IOErrorKind::AlreadyExists, "Runner was already started!")),
+            }
+        })
+    }
+
+    fn get_move<'a>(&'a mut self, board: &'a BoardStruct, player: Player, deadline: Instant) -> BoxFuture<'a, IOResult<usize>> {
+        Box::pin(async move {
+            match &mut self.runner {
+                Some(runner) => super::get_move(runner, board, &player, backend::seconds_left(deadline)).await,
+                None => Err(IOError::new(IOErrorKind::NotConnected, "Runner was asked for a move before being started!")),
+            }
+        })
+    }
+
+    fn stop(self: Box<Self>) -> BoxFuture<'static, IOResult<String>> {
+        Box::pin(async move {
+            match self.runner {
+                Some(runner) => super::kill_and_get_error(runner).await,
+                None => Ok(String::new()),
+            }
+        })
+    }
+}
@@ -0,0 +1,91 @@
+// The builtin players have to give up searching when they're told to, since
+// the server can't kill the threads they run on
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tungstenite_testings::othello::ai::{Strategy, MAX_DEPTH};
+use tungstenite_testings::othello::bitboard::Geometry;
+use tungstenite_testings::othello::endgame::{self, MAX_EMPTIES};
+use tungstenite_testings::othello::moves::{is_legal, legal_moves, make_move, next_player};
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+fn large_board() -> BoardStruct {
+    BoardStruct::initial(Geometry::for_size(10).unwrap())
+}
+
+// Plays the first legal move until there are `empties` empty squares left
+fn with_empties(empties: u32) -> (BoardStruct, Player) {
+    let mut board = BoardStruct::new();
+    let mut player = Player::Black;
+    loop {
+        let (black, white) = board.bitboards(&Player::Black);
+        if board.geometry().area() - (black | white).count_ones() <= empties {
+            break;
+        }
+        let square = legal_moves(&player, &board)[0];
+        make_move(&square, &player, &mut board).unwrap();
+        player = next_player(&board, &player).unwrap();
+    }
+    (board, player)
+}
+
+// Sets `stop` after `ms` milliseconds
+fn stop_after(ms: u64) -> (Arc<AtomicBool>, thread::JoinHandle<()>) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopper = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(ms));
+            stop.store(true, Ordering::Relaxed);
+        })
+    };
+    (stop, stopper)
+}
+
+#[test]
+fn stopped_search_still_moves() {
+    let board = large_board();
+    let stop = AtomicBool::new(true);
+    let square = Strategy::AlphaBeta(MAX_DEPTH).choose_move(&board, &Player::Black, false, &stop).unwrap();
+    assert!(is_legal(&square, &Player::Black, &board));
+}
+
+#[test]
+fn search_stops_partway() {
+    let board = large_board();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopper = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            stop.store(true, Ordering::Relaxed);
+        })
+    };
+
+    let start = Instant::now();
+    let square = Strategy::AlphaBeta(MAX_DEPTH).choose_move(&board, &Player::Black, false, &stop).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2), "search took {:?} to stop", start.elapsed());
+    assert!(is_legal(&square, &Player::Black, &board));
+    stopper.join().unwrap();
+}
+
+#[test]
+fn solver_stops_partway() {
+    let (board, player) = with_empties(MAX_EMPTIES);
+    let (stop, stopper) = stop_after(50);
+
+    let start = Instant::now();
+    assert_eq!(endgame::solve_until(&board, &player, false, &stop), None);
+    assert!(start.elapsed() < Duration::from_secs(2), "solver took {:?} to stop", start.elapsed());
+    stopper.join().unwrap();
+}
+
+#[test]
+fn stopped_endgame_still_moves() {
+    let (board, player) = with_empties(12);
+    let stop = AtomicBool::new(true);
+    let square = Strategy::AlphaBeta(MAX_DEPTH).choose_move(&board, &player, false, &stop).unwrap();
+    assert!(is_legal(&square, &player, &board));
+}
@@ -0,0 +1,117 @@
+// The solver's scores have to be exactly right. Known positions were checked
+// with a separate brute force search, and a plain negamax over the move
+// generator covers random positions near the end of the game
+use proptest::prelude::*;
+
+use tungstenite_testings::othello::bitboard::{self, Geometry};
+use tungstenite_testings::othello::endgame::solve;
+use tungstenite_testings::othello::moves::*;
+use tungstenite_testings::othello::notation::from_algebraic;
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+fn board(squares: &str) -> BoardStruct {
+    squares.parse().unwrap()
+}
+
+fn square(name: &str) -> Loc {
+    from_algebraic(BoardStruct::new().geometry(), name).unwrap()
+}
+
+// Board, player to move, whether it's anti-reversi, score, best moves
+const KNOWN : [(&str, Player, bool, i32, &[&str]); 8] = [
+    // One square left, which takes the last white disc
+    ("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@o.", Player::Black, false, 64, &["h8"]),
+    // White has to pass and Black takes it anyway
+    ("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@o.", Player::White, false, -64, &[]),
+    // Nobody can move, so the empties go to the winner
+    ("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@...", Player::Black, false, 64, &[]),
+    ("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@...", Player::White, false, -64, &[]),
+    ("o@@@ooooo@@@.@oo@o@@@@@o@@@oo@@o@@@@@o@o@@@ooooo.@@.@ooo.@oooooo", Player::Black, false, -30, &["d7"]),
+    ("@.oooo..ooo@ooo@o.@ooo@.@oo@o@@@@oooo@@@@oo@@@@o@oo@o@@.@o@@@@@@", Player::Black, false, 38, &["h7"]),
+    ("@.oooo..ooo@ooo@o.@ooo@.@oo@o@@@@oooo@@@@oo@@@@o@oo@o@@.@o@@@@@@", Player::Black, true, -6, &["b1", "b3"]),
+    ("o@ooo..oo@@oo.o.o@@oooo.o@o@o.o@oo@@@@@@oo@@oo@.oo@@o@..@@@@@ooo", Player::White, false, 0, &["h7"]),
+];
+
+#[test]
+fn known_positions() {
+    for (squares, player, anti, score, best) in KNOWN.iter() {
+        let solution = solve(&board(squares), player, *anti).unwrap();
+        assert_eq!(solution.score, *score, "{}", squares);
+        let best : Vec<Loc> = best.iter().map(|name| square(name)).collect();
+        match solution.best_move {
+            Some(sq) => assert!(best.contains(&sq), "{} played {}", squares, sq),
+            None => assert!(best.is_empty(), "{} passed", squares),
+        }
+    }
+}
+
+#[test]
+fn too_many_empties() {
+    assert_eq!(solve(&BoardStruct::new(), &Player::Black, false), None);
+    assert_eq!(solve(&board(KNOWN[4].0), &Player::Unknown, false), None);
+}
+
+// Final disc differential for `player`, empties to the winner
+fn final_score(board: &BoardStruct, player: &Player) -> i32 {
+    let (own, opp) = board.bitboards(player);
+    let diff = own.count_ones() as i32 - opp.count_ones() as i32;
+    let left = board.geometry().area() as i32 - (own | opp).count_ones() as i32;
+    diff.signum() * left + diff
+}
+
+// Every line to the end, no pruning or ordering
+fn negamax(board: &BoardStruct, player: &Player, anti: bool) -> i32 {
+    let squares = legal_moves(player, board);
+    if squares.is_empty() {
+        if !any_legal_moves(&player.opponent(), board) {
+            let score = final_score(board, player);
+            return if anti { -score } else { score };
+        }
+        return -negamax(board, &player.opponent(), anti);
+    }
+    squares.iter().map(|sq| {
+        let mut after = *board;
+        make_move(sq, player, &mut after).unwrap();
+        -negamax(&after, &player.opponent(), anti)
+    }).max().unwrap()
+}
+
+// A random game played until `empties` squares are left, or it ends
+fn near_the_end(size: usize, choices: &[usize], empties: u32) -> (BoardStruct, Player) {
+    let mut board = BoardStruct::initial(Geometry::for_size(size).unwrap());
+    let mut player = Player::Black;
+    for choice in choices.iter().cycle() {
+        let (own, opp) = board.bitboards(&player);
+        if board.geometry().area() - (own | opp).count_ones() <= empties {
+            break;
+        }
+        let squares = legal_moves(&player, &board);
+        make_move(&squares[choice % squares.len()], &player, &mut board).unwrap();
+        match next_player(&board, &player) {
+            Some(next) => player = next,
+            None => break,
+        }
+    }
+    (board, player)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn agrees_with_negamax(
+        size in prop::sample::select(bitboard::SIZES.to_vec()),
+        choices in prop::collection::vec(any::<usize>(), 1..20),
+        empties in 0u32..8,
+        anti in any::<bool>(),
+    ) {
+        let (board, player) = near_the_end(size, &choices, empties);
+        let solution = solve(&board, &player, anti).unwrap();
+        prop_assert_eq!(solution.score, negamax(&board, &player, anti));
+        if let Some(sq) = solution.best_move {
+            let mut after = board;
+            make_move(&sq, &player, &mut after).unwrap();
+            prop_assert_eq!(-negamax(&after, &player.opponent(), anti), solution.score);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// Checks on the position measurements, on boards small enough to count by hand
+use tungstenite_testings::othello::bitboard;
+use tungstenite_testings::othello::eval::{evaluate, heuristic, stable_discs};
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+// Rows from the top, '@' black, 'o' white and '.' empty
+fn board(rows: &[&str]) -> BoardStruct {
+    rows.concat().parse().unwrap()
+}
+
+const EMPTY_ROW : &str = "........";
+
+fn stable(board: &BoardStruct, player: Player) -> u32 {
+    let (own, opp) = board.bitboards(&player);
+    stable_discs(board.geometry(), own, opp).count_ones()
+}
+
+#[test]
+fn filled_edge_is_stable() {
+    let mut rows = [EMPTY_ROW; 8];
+    rows[0] = "@@@ooo@@";
+    let board = board(&rows);
+    assert_eq!(stable(&board, Player::Black), 5);
+    assert_eq!(stable(&board, Player::White), 3);
+}
+
+#[test]
+fn stable_from_the_corner() {
+    let mut rows = [EMPTY_ROW; 8];
+    // The corner holds b1 in place, c1 and the lone disc on the other
+    // edge can still be flipped
+    rows[0] = "@@o.....";
+    rows[4] = "@.......";
+    let board = board(&rows);
+    assert_eq!(stable(&board, Player::Black), 2);
+    assert_eq!(stable(&board, Player::White), 0);
+    let eval = evaluate(&board);
+    assert_eq!((eval.black.corners, eval.white.corners), (1, 0));
+}
+
+#[test]
+fn start_has_nothing_stable() {
+    let eval = evaluate(&BoardStruct::new());
+    assert_eq!(eval.black.stable + eval.white.stable, 0);
+    assert_eq!(eval.black.mobility, 4);
+    assert_eq!(eval.empties, 60);
+}
+
+#[test]
+fn parity_counts_odd_regions() {
+    let full = "@o@o@o@o";
+    let mut rows = [full; 8];
+    // One square on its own, a pair, and a group of three
+    rows[0] = ".o@o@o..";
+    rows[7] = "@o@o@...";
+    let eval = evaluate(&board(&rows));
+    assert_eq!(eval.empties, 6);
+    assert_eq!(eval.odd_regions, 2);
+
+    rows[7] = "@o@o@o..";
+    assert_eq!(evaluate(&board(&rows)).odd_regions, 1);
+    assert_eq!(evaluate(&board(&[full; 8])).odd_regions, 0);
+}
+
+// Whatever is good for one side is exactly as bad for the other
+#[test]
+fn heuristic_sign() {
+    let mut rows = [EMPTY_ROW; 8];
+    rows[0] = "@@o.....";
+    rows[3] = "...@o...";
+    rows[4] = "...o@...";
+    let board = board(&rows);
+    let (black, white) = board.bitboards(&Player::Black);
+    let g = &bitboard::STANDARD;
+    let score = heuristic(g, black, white);
+    assert!(score > 0, "{}", score);
+    assert_eq!(heuristic(g, white, black), -score);
+    let (black, white) = BoardStruct::new().bitboards(&Player::Black);
+    assert_eq!(heuristic(g, black, white), 0);
+}
@@ -0,0 +1,63 @@
+// GGF files come from uploads, so anything malformed has to come back as an
+// error rather than a panic
+use tungstenite_testings::othello::ggf::{parse_ggf, parse_ggf_all, to_ggf, GgfGame};
+use tungstenite_testings::othello::notation::from_algebraic;
+use tungstenite_testings::othello::record::GameRecord;
+use tungstenite_testings::othello::Player;
+
+#[test]
+fn too_short_to_be_a_game() {
+    for text in ["", "(;", ";)", "(;)", "  (;)  "].iter() {
+        assert!(parse_ggf(text).is_err(), "{:?} parsed", text);
+    }
+}
+
+#[test]
+fn unfinished_games_in_a_file() {
+    assert!(parse_ggf_all("(;)").is_err());
+    assert!(parse_ggf_all("(;GM[Othello];) (;)").is_err());
+    assert_eq!(parse_ggf_all("").unwrap().len(), 0);
+}
+
+#[test]
+fn empty_game() {
+    let game = parse_ggf("(;;)").unwrap();
+    assert_eq!(game.record.ply(), 0);
+}
+
+#[test]
+fn round_trip() {
+    let mut record = GameRecord::new();
+    for name in ["f5", "d6", "c3", "d3", "c4"].iter() {
+        let square = from_algebraic(record.board().geometry(), name).unwrap();
+        record.play(square).unwrap();
+    }
+    let text = to_ggf(&GgfGame::new("black", "white", record.clone()));
+    let parsed = parse_ggf(&text).unwrap();
+    assert_eq!(parsed.black, "black");
+    assert_eq!(parsed.record.board(), record.board());
+    assert_eq!(parsed.record.ply(), 5);
+}
+
+#[test]
+fn other_sizes_without_a_board() {
+    for size in [6usize, 10].iter() {
+        let game = parse_ggf(&format!("(;GM[Othello]TY[{}];)", size)).unwrap();
+        assert_eq!(game.record.board().size(), *size);
+        assert_eq!(game.record.tomove(), Player::Black);
+        assert_eq!(game.record.ply(), 0);
+        assert!(to_ggf(&game).contains(&format!("TY[{}]", size)));
+    }
+    let game = parse_ggf("(;GM[Othello]TY[10]B[e4];)").unwrap();
+    assert_eq!(game.record.board().size(), 10);
+    assert_eq!(game.record.ply(), 1);
+}
+
+#[test]
+fn board_after_moves() {
+    let board = "8 ---------------------------O*------*O--------------------------- *";
+    let text = format!("(;GM[Othello]TY[8]B[f5]W[d6]BO[{}];)", board);
+    assert!(parse_ggf(&text).is_err());
+    let text = format!("(;GM[Othello]TY[8]BO[{}]B[f5]W[d6];)", board);
+    assert_eq!(parse_ggf(&text).unwrap().record.ply(), 2);
+}
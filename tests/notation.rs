@@ -0,0 +1,83 @@
+// Every square has to come back as itself through each way of writing it,
+// on every board size, and anything that isn't a square has to be turned down
+use tungstenite_testings::othello::bitboard::{self, Geometry};
+use tungstenite_testings::othello::notation::*;
+
+fn geometries() -> impl Iterator<Item = &'static Geometry> {
+    bitboard::SIZES.iter().map(|size| Geometry::for_size(*size).unwrap())
+}
+
+#[test]
+fn squares_round_trip() {
+    for g in geometries() {
+        let squares : Vec<usize> = g.legal_spaces().collect();
+        assert_eq!(squares.len(), g.area() as usize);
+        for (index, sq) in squares.iter().enumerate() {
+            assert_eq!(to_index(g, *sq), Some(index));
+            assert_eq!(from_index(g, index), Some(*sq));
+            let name = to_algebraic(g, *sq).unwrap();
+            assert_eq!(from_algebraic(g, &name), Some(*sq));
+            assert_eq!(from_algebraic(g, &name.to_uppercase()), Some(*sq));
+        }
+        assert_eq!(from_index(g, g.area() as usize), None);
+    }
+}
+
+#[test]
+fn corners() {
+    for g in geometries() {
+        let last = g.size;
+        let width = g.width();
+        assert_eq!(from_algebraic(g, "a1"), Some(width + 1));
+        assert_eq!(from_algebraic(g, &format!("{}{}", (b'a' + last as u8 - 1) as char, last)), Some(width * last + last));
+    }
+    let standard = Geometry::for_size(8).unwrap();
+    assert_eq!(from_algebraic(standard, "h8"), Some(88));
+    assert_eq!(to_algebraic(standard, 45), Some("e4".to_string()));
+}
+
+#[test]
+fn move_lists_round_trip() {
+    for g in geometries() {
+        let squares : Vec<usize> = g.legal_spaces().collect();
+        let text = format_moves(g, &squares);
+        assert_eq!(parse_moves(g, &text).unwrap(), squares);
+    }
+    let large = Geometry::for_size(10).unwrap();
+    assert_eq!(parse_moves(large, "j10 a1\tA10").unwrap().len(), 3);
+}
+
+#[test]
+fn bad_squares() {
+    let standard = Geometry::for_size(8).unwrap();
+    let small = Geometry::for_size(6).unwrap();
+    // Bad columns
+    for name in ["i1", "z3", "11", "-1", "é4"].iter() {
+        assert_eq!(from_algebraic(standard, name), None, "{}", name);
+    }
+    assert_eq!(from_algebraic(small, "g1"), None);
+    // Off the board
+    for name in ["a0", "a9", "h10", "a01x"].iter() {
+        assert_eq!(from_algebraic(standard, name), None, "{}", name);
+    }
+    assert_eq!(from_algebraic(small, "a7"), None);
+    // Wrong length
+    for name in ["", "a", "a1b", "a1 "].iter() {
+        assert_eq!(from_algebraic(standard, name), None, "{}", name);
+    }
+    // Squares off the board, or on the border around it
+    for sq in [0usize, 10, 19, 90, 99, 100, 1000].iter() {
+        assert_eq!(to_index(standard, *sq), None, "{}", sq);
+        assert_eq!(to_algebraic(standard, *sq), None, "{}", sq);
+    }
+}
+
+#[test]
+fn bad_move_lists() {
+    let standard = Geometry::for_size(8).unwrap();
+    for moves in ["f", "f5d", "ff5", "f5 9", "f5i4", "f5d60", "f5?6"].iter() {
+        assert!(parse_moves(standard, moves).is_err(), "{:?} parsed", moves);
+    }
+    assert_eq!(parse_moves(standard, "").unwrap(), Vec::<usize>::new());
+    assert_eq!(parse_moves(standard, " f5 d6 ").unwrap(), vec![56, 64]);
+}
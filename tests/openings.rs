@@ -0,0 +1,68 @@
+// Every named line has to be recognised whichever way round it's played
+use tungstenite_testings::othello::bitboard;
+use tungstenite_testings::othello::notation::{from_algebraic, parse_moves};
+use tungstenite_testings::othello::openings::{recognize, OPENINGS};
+use tungstenite_testings::othello::record::GameRecord;
+use tungstenite_testings::othello::symmetry::SYMMETRIES;
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+// `moves` played from the usual start, by `first` and then alternately
+fn game(first: Player, moves: &[usize]) -> GameRecord {
+    let mut record = GameRecord::from_position(BoardStruct::new(), first);
+    for sq in moves {
+        record.play(*sq).unwrap();
+    }
+    record
+}
+
+#[test]
+fn lines_are_legal_and_distinct() {
+    let g = &bitboard::STANDARD;
+    let mut seen = Vec::new();
+    for opening in OPENINGS {
+        let line = parse_moves(g, opening.moves).unwrap();
+        assert_eq!(line[0], from_algebraic(g, "f5").unwrap(), "{}", opening.name);
+        assert!(!seen.contains(&line), "{} is listed twice", opening.moves);
+        game(Player::Black, &line);
+        seen.push(line);
+    }
+}
+
+#[test]
+fn recognised_in_every_orientation() {
+    let g = &bitboard::STANDARD;
+    for opening in OPENINGS {
+        let line = parse_moves(g, opening.moves).unwrap();
+        for sym in SYMMETRIES.iter() {
+            let turned : Vec<usize> = line.iter().map(|sq| sym.apply_square(g, *sq)).collect();
+            // Half of them swap the colors of the start, which makes it
+            // White's line
+            let first = if sym.apply_board(&BoardStruct::new()) == BoardStruct::new() {
+                Player::Black
+            } else {
+                Player::White
+            };
+            let record = game(first, &turned);
+            assert_eq!(recognize(&record), Some(opening), "{} as {:?}", opening.name, sym);
+        }
+    }
+}
+
+#[test]
+fn longest_line_wins() {
+    let g = &bitboard::STANDARD;
+    let record = game(Player::Black, &parse_moves(g, "f5d6c3d3c4f4c5b3c2e6").unwrap());
+    assert_eq!(recognize(&record).unwrap().moves, "f5d6c3d3c4f4c5b3c2");
+    let record = game(Player::Black, &parse_moves(g, "f5d6c3").unwrap());
+    assert_eq!(recognize(&record).unwrap().name, "Perpendicular Opening");
+}
+
+#[test]
+fn nothing_to_recognise() {
+    assert_eq!(recognize(&GameRecord::new()), None);
+    let small = BoardStruct::initial(bitboard::Geometry::for_size(6).unwrap());
+    let mut record = GameRecord::from_position(small, Player::Black);
+    let first = tungstenite_testings::othello::moves::legal_moves(&Player::Black, &small)[0];
+    record.play(first).unwrap();
+    assert_eq!(recognize(&record), None);
+}
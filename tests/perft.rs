@@ -0,0 +1,82 @@
+// Move generation checked against known perft numbers. The 8x8 numbers are
+// the usual ones for Othello (OEIS A124004), the others were cross-checked
+// against a plain mailbox implementation. The deep ones take a while, run
+// them with `cargo test --release -- --ignored`
+use tungstenite_testings::othello::bitboard::Geometry;
+use tungstenite_testings::othello::perft::{counts, perft};
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+const STANDARD : [u64; 11] = [4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288, 24571284, 212258800];
+const SMALL : [u64; 9] = [4, 12, 56, 244, 1364, 7604, 47740, 308716, 2114912];
+const LARGE : [u64; 9] = [4, 12, 56, 244, 1396, 8200, 55180, 392268, 3045812];
+
+fn check(size: usize, expected: &[u64]) {
+    let geometry = Geometry::for_size(size).unwrap();
+    assert_eq!(counts(geometry, expected.len() as u32), expected, "{}x{} perft", size, size);
+}
+
+#[test]
+fn perft_standard() {
+    check(8, &STANDARD[..8]);
+}
+
+#[test]
+fn perft_small() {
+    check(6, &SMALL[..7]);
+}
+
+#[test]
+fn perft_large() {
+    check(10, &LARGE[..7]);
+}
+
+#[test]
+#[ignore]
+fn perft_standard_deep() {
+    check(8, &STANDARD);
+}
+
+#[test]
+#[ignore]
+fn perft_small_deep() {
+    check(6, &SMALL);
+}
+
+#[test]
+#[ignore]
+fn perft_large_deep() {
+    check(10, &LARGE);
+}
+
+// Passes have to count as a move, and finished games as a single leaf
+#[test]
+fn perft_passes_and_game_over() {
+    // White has nothing, so black's c1 comes after a pass and ends the game
+    let board : BoardStruct = concat!(
+        "@o......",
+        "........",
+        "........",
+        "........",
+        "........",
+        "........",
+        "........",
+        "........",
+    ).parse().unwrap();
+    assert_eq!(perft(&board, &Player::White, 1), 1);
+    assert_eq!(perft(&board, &Player::White, 2), 1);
+    assert_eq!(perft(&board, &Player::White, 3), 1);
+    assert_eq!(perft(&board, &Player::Black, 1), 1);
+
+    // Nobody can move, which is one finished game at any depth
+    let over : BoardStruct = concat!(
+        "@@......",
+        "........",
+        "........",
+        "........",
+        "........",
+        "........",
+        "........",
+        "........",
+    ).parse().unwrap();
+    assert_eq!(perft(&over, &Player::White, 5), 1);
+}
@@ -0,0 +1,90 @@
+// Stepping back and forth through a game has to land on exactly the
+// positions the moves led to
+use tungstenite_testings::othello::notation::from_algebraic;
+use tungstenite_testings::othello::record::GameRecord;
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+fn square(name: &str) -> usize {
+    from_algebraic(BoardStruct::new().geometry(), name).unwrap()
+}
+
+// A game with `names` played on the standard board
+fn played(names: &[&str]) -> GameRecord {
+    let mut game = GameRecord::new();
+    for name in names {
+        game.play(square(name)).unwrap();
+    }
+    game
+}
+
+#[test]
+fn undo_redo_round_trip() {
+    let mut game = played(&["f5", "d6", "c3", "d3", "c4"]);
+    let boards : Vec<(BoardStruct, Player)> = game.positions().collect();
+    assert_eq!(boards.len(), 6);
+
+    for ply in (0..5).rev() {
+        let undone = game.undo().unwrap().clone();
+        assert_eq!(game.ply(), ply);
+        assert_eq!((*game.board(), game.tomove()), boards[ply]);
+        assert_eq!(game.tomove(), undone.player);
+    }
+    assert!(game.undo().is_none());
+    assert_eq!(*game.board(), BoardStruct::new());
+
+    for (ply, position) in boards.iter().enumerate().skip(1) {
+        game.redo().unwrap();
+        assert_eq!(game.ply(), ply);
+        assert_eq!((*game.board(), game.tomove()), *position);
+    }
+    assert!(game.redo().is_none());
+}
+
+#[test]
+fn new_move_clears_redo() {
+    let mut game = played(&["f5", "d6", "c3"]);
+    game.undo();
+    game.undo();
+    assert!(game.can_redo());
+
+    game.play(square("f4")).unwrap();
+    assert!(!game.can_redo());
+    assert!(game.redo().is_none());
+    assert_eq!(game.ply(), 2);
+    assert_eq!(game.moves().len(), 2);
+    assert_eq!(game.last_move().unwrap().square, Some(square("f4")));
+}
+
+#[test]
+fn replay_to_bounds() {
+    let mut game = played(&["f5", "d6", "c3", "d3"]);
+    let end = *game.board();
+
+    assert!(game.replay_to(0));
+    assert_eq!(game.ply(), 0);
+    assert_eq!(*game.board(), BoardStruct::new());
+    assert_eq!(game.tomove(), Player::Black);
+
+    assert!(game.replay_to(4));
+    assert_eq!(game.ply(), 4);
+    assert_eq!(*game.board(), end);
+
+    // Past the end does nothing
+    assert!(!game.replay_to(5));
+    assert_eq!(game.ply(), 4);
+
+    game.replay_to(2);
+    assert!(game.replay_to(4));
+    assert_eq!(*game.board(), end);
+}
+
+#[test]
+fn positions_stop_at_the_current_move() {
+    let mut game = played(&["f5", "d6", "c3"]);
+    game.undo();
+    let positions : Vec<(BoardStruct, Player)> = game.positions().collect();
+    assert_eq!(positions.len(), 3);
+    assert_eq!(positions[0], (BoardStruct::new(), Player::Black));
+    assert_eq!(*positions.last().unwrap(), (*game.board(), game.tomove()));
+    assert_eq!(positions[1].1, Player::White);
+}
@@ -0,0 +1,45 @@
+// Pictures of the usual start, where every disc and mark is easy to place.
+// With 40 pixel squares the board starts 24 pixels in, so square centers
+// are at 44, 84, ..., 324
+use tungstenite_testings::othello::bitboard;
+use tungstenite_testings::othello::notation::from_algebraic;
+use tungstenite_testings::othello::render::{svg, SvgOptions};
+use tungstenite_testings::othello::BoardStruct;
+
+fn square(name: &str) -> usize {
+    from_algebraic(&bitboard::STANDARD, name).unwrap()
+}
+
+fn disc(x: u32, y: u32, fill: &str) -> String {
+    format!("<circle cx=\"{}\" cy=\"{}\" r=\"16.8\" fill=\"{}\" stroke=\"#000\"/>", x, y, fill)
+}
+
+#[test]
+fn start_position() {
+    let out = svg(&BoardStruct::new(), &SvgOptions::default());
+    assert!(out.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"368\" height=\"368\" viewBox=\"0 0 368 368\">"));
+    assert!(out.ends_with("</svg>"));
+    assert_eq!(out.matches("<circle").count(), 4);
+    // d4 and e5 white, e4 and d5 black
+    assert!(out.contains(&disc(164, 164, "#fff")));
+    assert!(out.contains(&disc(204, 204, "#fff")));
+    assert!(out.contains(&disc(204, 164, "#000")));
+    assert!(out.contains(&disc(164, 204, "#000")));
+    // Seven lines each way, and a label for every row and column
+    assert_eq!(out.matches("<line").count(), 14);
+    assert!(out.contains(">h</text>") && out.contains(">8</text>"));
+}
+
+#[test]
+fn marks() {
+    let options = SvgOptions {
+        last_move: Some(square("f5")),
+        legal_moves: vec![square("d6"), square("f4"), 999],
+        ..SvgOptions::default()
+    };
+    let out = svg(&BoardStruct::new(), &options);
+    assert!(out.contains("<circle cx=\"244\" cy=\"204\" r=\"4\" fill=\"#e53935\"/>"));
+    // The square that isn't on the board is left out
+    assert_eq!(out.matches("fill-opacity").count(), 2);
+    assert!(out.contains("<circle cx=\"164\" cy=\"244\" r=\"4."));
+}
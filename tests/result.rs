@@ -0,0 +1,82 @@
+// What a GameResult says about a game, both played out and forfeited
+use tungstenite_testings::othello::result::{EndReason, GameResult, Outcome};
+use tungstenite_testings::othello::variant::Variant;
+use tungstenite_testings::othello::{BoardStruct, Player};
+
+// Rows from the top, '@' black, 'o' white and '.' empty
+fn board(rows: &[&str]) -> BoardStruct {
+    rows.concat().parse().unwrap()
+}
+
+// Black ahead 10 to 3, with moves still to play
+fn midgame() -> BoardStruct {
+    board(&[
+        "@@@@@...", "@@@@@...", "ooo.....", "........",
+        "........", "........", "........", "........",
+    ])
+}
+
+#[test]
+fn forfeits_go_to_the_other_side() {
+    let reasons = [
+        EndReason::Timeout, EndReason::IllegalMove, EndReason::Crash,
+        EndReason::ResourceLimit, EndReason::Resignation, EndReason::Disconnect,
+    ];
+    for reason in reasons.iter() {
+        // Whatever the board says
+        let result = GameResult::forfeit(&Player::Black, *reason, &midgame());
+        assert_eq!(result.outcome, Outcome::WhiteWin);
+        assert_eq!(result.reason, *reason);
+        assert_eq!((result.black_discs, result.white_discs), (10, 3));
+        assert!(result.is_forfeit());
+
+        let result = GameResult::forfeit(&Player::White, *reason, &BoardStruct::new());
+        assert_eq!(result.outcome.winner(), Player::Black);
+        assert_eq!((result.black_discs, result.white_discs), (2, 2));
+    }
+}
+
+#[test]
+fn finished_games_count_discs() {
+    let standard = Variant::default();
+    let result = GameResult::finished(&midgame(), &standard);
+    assert_eq!(result, GameResult {
+        outcome: Outcome::BlackWin,
+        reason: EndReason::Normal,
+        black_discs: 10,
+        white_discs: 3,
+    });
+    assert!(!result.is_forfeit());
+
+    let drawn = GameResult::finished(&BoardStruct::new(), &standard);
+    assert_eq!(drawn.outcome, Outcome::Draw);
+    assert_eq!(drawn.outcome.winner(), Player::Unknown);
+}
+
+#[test]
+fn results_on_the_wire() {
+    let result = GameResult::forfeit(&Player::White, EndReason::Resignation, &midgame());
+    let json = serde_json::to_value(&result).unwrap();
+    assert_eq!(json, serde_json::json!({
+        "outcome": "black",
+        "reason": "resignation",
+        "black_discs": 10,
+        "white_discs": 3,
+    }));
+}
+
+// The same boards, but whoever has fewer discs wins
+#[test]
+fn anti_reversi_goes_to_fewer_discs() {
+    let anti = Variant::new(8, true).unwrap();
+    let result = GameResult::finished(&midgame(), &anti);
+    assert_eq!(result.outcome, Outcome::WhiteWin);
+    assert_eq!((result.black_discs, result.white_discs), (10, 3));
+    assert_eq!(anti.winner(&midgame()), Player::White);
+
+    assert_eq!(GameResult::finished(&BoardStruct::new(), &anti).outcome, Outcome::Draw);
+
+    // Forfeits don't care which way round winning goes
+    let result = GameResult::forfeit(&Player::White, EndReason::Timeout, &midgame());
+    assert_eq!(result.outcome, Outcome::BlackWin);
+}
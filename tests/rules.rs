@@ -0,0 +1,138 @@
+// Properties of the rules that have to hold in any position. Positions come
+// from playing out random games, on every board size
+use proptest::prelude::*;
+use std::collections::BTreeSet;
+
+use tungstenite_testings::othello::bitboard::{self, Bits, Geometry};
+use tungstenite_testings::othello::moves::*;
+use tungstenite_testings::othello::perft::perft;
+use tungstenite_testings::othello::symmetry::{self, SYMMETRIES};
+use tungstenite_testings::othello::{hash, BoardParseError, BoardStruct, Piece, Player};
+
+// A position partway through a game, with the player to move. `choices`
+// picks each move out of the legal ones, and the game stops when they run
+// out or the game ends
+fn play_out(size: usize, choices: &[usize]) -> (BoardStruct, Player) {
+    let mut board = BoardStruct::initial(Geometry::for_size(size).unwrap());
+    let mut player = Player::Black;
+    for choice in choices {
+        let squares = legal_moves(&player, &board);
+        if squares.is_empty() {
+            break;
+        }
+        make_move(&squares[choice % squares.len()], &player, &mut board).unwrap();
+        match next_player(&board, &player) {
+            Some(next) => player = next,
+            None => break,
+        }
+    }
+    (board, player)
+}
+
+fn position() -> impl Strategy<Value = (BoardStruct, Player)> {
+    (
+        prop::sample::select(bitboard::SIZES.to_vec()),
+        prop::collection::vec(any::<usize>(), 0..100),
+    ).prop_map(|(size, choices)| play_out(size, &choices))
+}
+
+fn discs(board: &BoardStruct, player: &Player) -> (u32, u32) {
+    let (own, opp) = board.bitboards(player);
+    (own.count_ones(), opp.count_ones())
+}
+
+proptest! {
+    // Every move adds exactly one disc and only turns over the opponent's,
+    // and taking it back gives the same board
+    #[test]
+    fn moves_conserve_discs((board, player) in position()) {
+        let (own, opp) = discs(&board, &player);
+        for sq in legal_moves(&player, &board) {
+            let mut after = board;
+            let flipped = make_move(&sq, &player, &mut after).unwrap();
+            let flips = flipped.len() as u32;
+            prop_assert!(flips > 0);
+            prop_assert_eq!(discs(&after, &player), (own + 1 + flips, opp - flips));
+            prop_assert_eq!(after.get(sq), (&player).into());
+
+            let (black, white) = after.bitboards(&Player::Black);
+            prop_assert_eq!(after.hash(), hash::hash_discs(black, white));
+
+            unmake_move(&MoveRecord {player, square: Some(sq), flipped}, &mut after);
+            prop_assert_eq!(after, board);
+            prop_assert_eq!(after.hash(), board.hash());
+        }
+    }
+
+    // Boards that fit in a u64 get the same moves and flips worked out in one
+    #[test]
+    fn narrow_bits_agree((board, player) in position()) {
+        let g = board.geometry();
+        prop_assume!(g.fits_u64());
+        let (own, opp) = board.bitboards(&player);
+        let (own64, opp64) = (u64::narrow(own), u64::narrow(opp));
+        let moves = g.legal_moves(own, opp);
+        prop_assert_eq!(g.legal_moves(own64, opp64).widen(), moves);
+        for bit in g.squares(moves).filter_map(|sq| g.square_to_bit(sq)) {
+            prop_assert_eq!(g.flips(own64, opp64, u64::bit(bit)).widen(), g.flips(own, opp, u128::bit(bit)));
+        }
+    }
+
+    // Illegal squares leave the board alone
+    #[test]
+    fn illegal_moves_change_nothing((board, player) in position(), square in 0usize..200) {
+        if !is_legal(&square, &player, &board) {
+            let mut after = board;
+            prop_assert!(make_move(&square, &player, &mut after).is_err());
+            prop_assert_eq!(after, board);
+        }
+    }
+
+    // Rotating or reflecting a position rotates or reflects its moves, and
+    // nothing else about it changes
+    #[test]
+    fn symmetric_positions_play_the_same((board, player) in position()) {
+        let g = board.geometry();
+        let squares = legal_moves(&player, &board);
+        let nodes = perft(&board, &player, 2);
+        let canonical = symmetry::canonical(&board).0;
+        for sym in SYMMETRIES.iter() {
+            let turned = sym.apply_board(&board);
+            let expected : BTreeSet<_> = squares.iter().map(|sq| sym.apply_square(g, *sq)).collect();
+            let found : BTreeSet<_> = legal_moves(&player, &turned).into_iter().collect();
+            prop_assert_eq!(found, expected);
+            prop_assert_eq!(score(&player, &turned), score(&player, &board));
+            prop_assert_eq!(perft(&turned, &player, 2), nodes);
+            prop_assert_eq!(symmetry::canonical(&turned).0, canonical);
+            prop_assert_eq!(sym.inverse().apply_board(&turned), board);
+        }
+    }
+
+    // Boards survive being sent over the wire, with or without the border
+    #[test]
+    fn boards_round_trip((board, _) in position()) {
+        let json = serde_json::to_string(&board).unwrap();
+        prop_assert_eq!(serde_json::from_str::<BoardStruct>(&json).unwrap(), board);
+
+        let bordered : String = serde_json::from_str(&json).unwrap();
+        let bare : String = bordered.chars().filter(|c| *c != '?').collect();
+        prop_assert_eq!(bare.parse::<BoardStruct>().unwrap(), board);
+        prop_assert_eq!(BoardStruct::parse_sized(&bare, board.size()).unwrap(), board);
+    }
+}
+
+// One bad board for each way parsing can fail
+#[test]
+fn board_parse_errors() {
+    let start : String = serde_json::from_str(&serde_json::to_string(&BoardStruct::new()).unwrap()).unwrap();
+    let with = |index: usize, c: char| -> String {
+        start.chars().enumerate().map(|(i, old)| if i == index { c } else { old }).collect()
+    };
+
+    assert_eq!("@o.".parse::<BoardStruct>(), Err(BoardParseError::WrongLength(3)));
+    let small = ".".repeat(36);
+    assert_eq!(BoardStruct::parse_sized(&small, 8), Err(BoardParseError::WrongSize {found: 6, expected: 8}));
+    assert_eq!(with(45, 'x').parse::<BoardStruct>(), Err(BoardParseError::BadPiece {index: 45, found: 'x'}));
+    assert_eq!(with(9, '@').parse::<BoardStruct>(), Err(BoardParseError::BadBorder {index: 9, found: Piece::BLACK}));
+    assert_eq!(with(11, '?').parse::<BoardStruct>(), Err(BoardParseError::BadInterior {index: 11}));
+}
@@ -0,0 +1,70 @@
+// Opening lists have to play out legally, and not hold the same opening twice
+// turned around
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use tungstenite_testings::othello::xot::{builtin_openings, load_openings, opening_game, parse_openings, random_opening};
+
+#[test]
+fn lines_play_out() {
+    let openings = parse_openings("f5d6c3d3c4f4f6f3\n\n  f5f6e6f4  \n").unwrap();
+    assert_eq!(openings.len(), 2);
+    assert_eq!(openings[0].moves().len(), 8);
+    assert_eq!(openings[1].moves().len(), 4);
+}
+
+#[test]
+fn illegal_lines_are_rejected() {
+    assert!(parse_openings("f5d6c3d3c4f4f6f3\nf5f5").is_err());
+    assert!(parse_openings("a1").is_err());
+    assert!(parse_openings("f5z9").is_err());
+}
+
+#[test]
+fn symmetric_duplicates_are_rejected() {
+    for line in ["f5d6", "e6f4", "d3c5"] {
+        assert!(parse_openings(line).is_ok());
+    }
+    // The same opening, flipped over either diagonal
+    assert!(parse_openings("f5d6\ne6f4").is_err());
+    assert!(parse_openings("f5d6\nd3c5").is_err());
+    // Different positions
+    assert!(parse_openings("f5d6\nf5f6\nf5f4").is_ok());
+}
+
+#[test]
+fn random_openings_come_from_the_list() {
+    let openings = parse_openings("f5d6c3d3c4f4f6f3\nf5f6e6f4\nf5f4e3f6d3").unwrap();
+    let picked = random_opening(&openings, &mut StdRng::seed_from_u64(5)).unwrap();
+    assert!(openings.iter().any(|game| game.moves() == picked.moves()));
+    let again = random_opening(&openings, &mut StdRng::seed_from_u64(5)).unwrap();
+    assert_eq!(picked.moves(), again.moves());
+    assert!(random_opening(&[], &mut StdRng::seed_from_u64(5)).is_err());
+    assert!(opening_game("f5d6c3").is_ok());
+}
+
+// The list the server uses when it isn't given one
+#[test]
+fn builtin_list_is_valid() {
+    let openings = builtin_openings().unwrap();
+    assert_eq!(openings.len(), 3000);
+    assert!(openings.iter().all(|game| game.moves().len() == 8));
+}
+
+// And the one it's given, if there is one. Naming a file that isn't there
+// is a mistake, not a reason to skip
+#[test]
+fn xot_file_is_valid() {
+    if let Some(path) = env::var_os("OTHELLO_XOT").map(PathBuf::from) {
+        let openings = load_openings(&path).unwrap();
+        assert!(!openings.is_empty());
+        assert!(openings.iter().all(|game| game.moves().len() == 8));
+    }
+}
+
+#[test]
+fn missing_files_are_errors() {
+    assert!(load_openings(Path::new("no/such/openings.txt")).is_err());
+}